@@ -2,13 +2,16 @@ use std::{env, fs, path::Path};
 
 use grapple_hook::{
     devices::{
+        events::ProviderDeviceEvent,
         flexican::{FlexiCanRequest, FlexiCanResponse},
         lasercan::{LaserCanRequest, LaserCanResponse},
         mitocandria::{MitocandriaRequest, MitocandriaResponse},
         provider_manager::{ProviderManagerRequest, ProviderManagerResponse},
+        replay::{ReplayRequest, ReplayResponse},
         roborio::daemon::{RoboRioDaemonRequest, RoboRioDaemonResponse},
+        simulator::{SimulatorRequest, SimulatorResponse},
         FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, OldVersionDeviceRequest,
-        OldVersionDeviceResponse,
+        OldVersionDeviceResponse, UnknownGrappleDeviceRequest, UnknownGrappleDeviceResponse,
     },
     updates::LightReleaseResponse,
 };
@@ -22,6 +25,9 @@ struct MegaSchema {
     old_version_req: OldVersionDeviceRequest,
     old_version_rsp: OldVersionDeviceResponse,
 
+    unknown_req: UnknownGrappleDeviceRequest,
+    unknown_rsp: UnknownGrappleDeviceResponse,
+
     firmware_req: FirmwareUpgradeDeviceRequest,
     firmware_rsp: FirmwareUpgradeDeviceResponse,
 
@@ -37,7 +43,13 @@ struct MegaSchema {
     roborio_req: RoboRioDaemonRequest,
     roborio_rsp: RoboRioDaemonResponse,
 
+    replay_req: ReplayRequest,
+    replay_rsp: ReplayResponse,
+    simulator_req: SimulatorRequest,
+    simulator_rsp: SimulatorResponse,
+
     light_release_response: LightReleaseResponse,
+    device_event: ProviderDeviceEvent,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -0,0 +1,770 @@
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use env_logger::Builder;
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+use grapple_frc_msgs::grapple::lasercan::{
+    LaserCanRangingMode, LaserCanRoi, LaserCanRoiU4, LaserCanTimingBudget,
+};
+use grapple_frc_msgs::grapple::mitocandria::{
+    MitocandriaAdjustableChannelRequest, MitocandriaSwitchableChannelRequest,
+};
+use grapple_hook::devices::{
+    bulk_upgrade::BulkUpgradeState,
+    device_manager::{
+        CallReport, DeviceId, DeviceManagerRequest, DeviceManagerResponse, DevicePresence, Domain,
+    },
+    events::ProviderDeviceEvent,
+    flexican::FlexiCanRequest,
+    lasercan::LaserCanRequest,
+    mitocandria::MitocandriaRequest,
+    provider::{ProviderInfo, WrappedDeviceProviderRequest, WrappedDeviceProviderResponse},
+    provider_manager::{ProviderManager, ProviderManagerRequest, ProviderManagerResponse},
+    retry::RetryPolicy,
+    roborio::daemon::RoboRioDaemonRequest,
+    snapshot::ConfigFormat,
+    DeviceInfo, GrappleDeviceRequest,
+};
+use grapple_hook::updates::{self, UpdateChannel};
+use tokio::sync::{broadcast, Mutex};
+
+/// Headless GrappleHook, for configuring devices from scripts and CI rigs.
+#[derive(Parser)]
+#[command(name = "grapple-hook-cli", version)]
+struct Cli {
+    /// Address of the provider to connect to, e.g. the RoboRIO IP or a USB serial port.
+    #[arg(short, long, default_value = "172.22.11.2")]
+    provider: String,
+
+    /// Don't deploy the GrappleHook daemon when connecting to a RoboRIO.
+    #[arg(long)]
+    no_daemon: bool,
+
+    /// How long to wait for devices to enumerate after connecting.
+    #[arg(long, default_value_t = 1500)]
+    wait_ms: u64,
+
+    /// How long to wait for each reply from a device. Only applies to this command, the
+    /// provider's retry policy is left as is.
+    #[arg(long)]
+    timeout_ms: Option<u64>,
+
+    /// How many times to send each request to a device. Only applies to this command.
+    #[arg(long)]
+    attempts: Option<u32>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Local(LocalCommand),
+    #[command(flatten)]
+    Device(DeviceCommand),
+}
+
+/// Commands that don't need a connection to a provider
+#[derive(Subcommand)]
+enum LocalCommand {
+    /// List all available providers
+    Providers,
+    /// Import a folder of firmware bundles into the firmware cache, for use without internet
+    ImportFirmware { path: String },
+    /// Show where update checks and firmware downloads come from, changing any options given
+    UpdateSource {
+        /// Base of the GitHub-style release API, e.g. a local mirror
+        #[arg(long)]
+        endpoint: Option<String>,
+        #[arg(long)]
+        channel: Option<Channel>,
+        /// Proxy for update requests. Pass an empty string to stop using one.
+        #[arg(long)]
+        proxy: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DeviceCommand {
+    /// List all devices on the provider
+    Devices,
+    /// Give devices that share an ID with another device of the same model a free ID
+    ResolveIdConflicts,
+    /// Print device events (arrivals, departures, status frames, ...) as JSON lines as they
+    /// happen, until interrupted
+    Watch {
+        /// Stop after this many seconds
+        #[arg(long)]
+        seconds: Option<u64>,
+    },
+    /// Blink the status LED of a device
+    Blink { serial: u32 },
+    /// Set the CAN ID of a device
+    SetId { serial: u32, id: u8 },
+    /// Set the name of a device
+    SetName { serial: u32, name: String },
+    /// Commit the current configuration of a device to EEPROM
+    Commit { serial: u32 },
+    /// LaserCAN operations
+    #[command(subcommand)]
+    Lasercan(LaserCanCommand),
+    /// MitoCANdria operations
+    #[command(subcommand)]
+    Mitocandria(MitocandriaCommand),
+    /// FlexiCAN operations
+    #[command(subcommand)]
+    Flexican(FlexiCanCommand),
+    /// Send a raw JSON RPC to a device
+    Call {
+        serial: u32,
+        json: String,
+        /// Also print how many requests the call made and how many attempts they took
+        #[arg(long)]
+        stats: bool,
+    },
+    /// Save the configuration of every device to a JSON or TOML file
+    ExportConfig { path: String },
+    /// Apply a configuration file saved with export-config, matching devices by serial
+    ImportConfig {
+        path: String,
+        /// Commit the applied configuration to EEPROM
+        #[arg(long)]
+        commit: bool,
+    },
+    /// Flash a firmware file to every device of the given model, one at a time
+    UpgradeAll { model: Model, path: String },
+    /// Show the version, release notes and supported models of a firmware bundle
+    BundleInfo { path: String },
+}
+
+#[derive(Subcommand)]
+enum LaserCanCommand {
+    Status {
+        serial: u32,
+    },
+    SetRange {
+        serial: u32,
+        mode: RangingMode,
+    },
+    SetRoi {
+        serial: u32,
+        x: u8,
+        y: u8,
+        w: u8,
+        h: u8,
+    },
+    SetTimingBudget {
+        serial: u32,
+        /// Timing budget in milliseconds (20, 33, 50, or 100)
+        budget_ms: u8,
+    },
+}
+
+#[derive(Subcommand)]
+enum MitocandriaCommand {
+    Status {
+        serial: u32,
+    },
+    SetSwitchable {
+        serial: u32,
+        channel: u8,
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    SetAdjustable {
+        serial: u32,
+        channel: u8,
+        voltage_mv: u16,
+    },
+    Calibrate {
+        serial: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum FlexiCanCommand {
+    Status { serial: u32 },
+    ChannelName { serial: u32, channel: u8 },
+    StartBridge { serial: u32, channel: u8 },
+    StopBridge { serial: u32, channel: u8 },
+}
+
+#[derive(Clone, ValueEnum)]
+enum Model {
+    Lasercan,
+    Mitocandria,
+    Flexican,
+}
+
+#[derive(Clone, ValueEnum)]
+enum Channel {
+    Stable,
+    Prerelease,
+}
+
+#[derive(Clone, ValueEnum)]
+enum RangingMode {
+    Short,
+    Long,
+}
+
+struct Session {
+    manager: ProviderManager,
+    address: String,
+    /// Subscribed before connecting, so devices found during enumeration are seen by `watch`
+    events: Mutex<broadcast::Receiver<ProviderDeviceEvent>>,
+    /// Retry policy for device calls, if the command line overrides the provider's
+    retry: Option<RetryPolicy>,
+}
+
+impl Session {
+    async fn provider(
+        &self,
+        msg: WrappedDeviceProviderRequest,
+    ) -> anyhow::Result<WrappedDeviceProviderResponse> {
+        match self
+            .manager
+            .rpc_process(ProviderManagerRequest::provider {
+                address: self.address.clone(),
+                msg,
+            })
+            .await?
+        {
+            ProviderManagerResponse::provider(rsp) => Ok(rsp),
+            _ => anyhow::bail!("Unexpected response from provider manager"),
+        }
+    }
+
+    async fn info(&self) -> anyhow::Result<ProviderInfo> {
+        match self.provider(WrappedDeviceProviderRequest::info {}).await? {
+            WrappedDeviceProviderResponse::info(info) => Ok(info),
+            _ => anyhow::bail!("Unexpected response from provider"),
+        }
+    }
+
+    async fn device_manager(
+        &self,
+        req: DeviceManagerRequest,
+    ) -> anyhow::Result<DeviceManagerResponse> {
+        match self
+            .provider(WrappedDeviceProviderRequest::device_manager_call { req })
+            .await?
+        {
+            WrappedDeviceProviderResponse::device_manager_call(rsp) => Ok(rsp),
+            _ => anyhow::bail!("Unexpected response from provider"),
+        }
+    }
+
+    async fn devices(
+        &self,
+    ) -> anyhow::Result<
+        Vec<(
+            Domain,
+            DeviceId,
+            DeviceInfo,
+            String,
+            Vec<u32>,
+            DevicePresence,
+        )>,
+    > {
+        match self
+            .device_manager(DeviceManagerRequest::devices {})
+            .await?
+        {
+            DeviceManagerResponse::devices(map) => Ok(map
+                .into_iter()
+                .flat_map(|(domain, devices)| {
+                    devices
+                        .into_iter()
+                        .map(move |(id, info, class, conflicts, presence)| {
+                            (domain.clone(), id, info, class, conflicts, presence)
+                        })
+                })
+                .collect()),
+            _ => anyhow::bail!("Unexpected response from device manager"),
+        }
+    }
+
+    async fn locate(&self, serial: u32) -> anyhow::Result<(Domain, DeviceId)> {
+        let (domain, device_id, _, _, _, _) = self
+            .devices()
+            .await?
+            .into_iter()
+            .find(|(_, _, info, _, _, _)| info.serial == Some(serial))
+            .ok_or(anyhow::anyhow!("No device with serial {}", serial))?;
+        Ok((domain, device_id))
+    }
+
+    async fn call<T: serde::Serialize>(
+        &self,
+        serial: u32,
+        data: T,
+    ) -> anyhow::Result<serde_json::Value> {
+        let (domain, device_id) = self.locate(serial).await?;
+
+        match self
+            .device_manager(DeviceManagerRequest::call {
+                domain,
+                device_id,
+                data: serde_json::to_value(data)?,
+                retry: self.retry.clone(),
+            })
+            .await?
+        {
+            DeviceManagerResponse::call(rsp) => Ok(rsp),
+            _ => anyhow::bail!("Unexpected response from device manager"),
+        }
+    }
+
+    async fn call_with_stats(
+        &self,
+        serial: u32,
+        data: serde_json::Value,
+    ) -> anyhow::Result<CallReport> {
+        let (domain, device_id) = self.locate(serial).await?;
+
+        match self
+            .device_manager(DeviceManagerRequest::call_with_stats {
+                domain,
+                device_id,
+                data,
+                retry: self.retry.clone(),
+            })
+            .await?
+        {
+            DeviceManagerResponse::call_with_stats(rsp) => Ok(rsp),
+            _ => anyhow::bail!("Unexpected response from device manager"),
+        }
+    }
+
+    async fn grapple(
+        &self,
+        serial: u32,
+        msg: GrappleDeviceRequest,
+    ) -> anyhow::Result<serde_json::Value> {
+        // All root devices that expose the common Grapple RPCs do so under "grapple"
+        self.call(
+            serial,
+            serde_json::json!({ "method": "grapple", "data": { "msg": msg } }),
+        )
+        .await
+    }
+}
+
+async fn wait_for_provider(manager: &ProviderManager, address: &str) -> anyhow::Result<()> {
+    // USB providers are only picked up on a detect pass, which is rate limited.
+    for _ in 0..10 {
+        if let ProviderManagerResponse::providers(providers) = manager
+            .rpc_process(ProviderManagerRequest::providers {})
+            .await?
+        {
+            if providers.contains_key(address) {
+                return Ok(());
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    anyhow::bail!("No provider with address {}", address)
+}
+
+fn config_format(path: &str) -> ConfigFormat {
+    match std::path::Path::new(path)
+        .extension()
+        .and_then(|x| x.to_str())
+    {
+        Some("toml") => ConfigFormat::Toml,
+        _ => ConfigFormat::Json,
+    }
+}
+
+fn print_json(value: &serde_json::Value) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+async fn run(session: &Session, command: DeviceCommand) -> anyhow::Result<()> {
+    match command {
+        DeviceCommand::Devices => print_json(&serde_json::to_value(
+            session
+                .devices()
+                .await?
+                .into_iter()
+                .map(|(domain, id, info, class, conflicts, presence)| {
+                    serde_json::json!({
+                        "domain": domain,
+                        "id": id,
+                        "info": info,
+                        "class": class,
+                        "id_conflicts": conflicts,
+                        "online": presence.online,
+                        "last_seen": presence.last_seen
+                    })
+                })
+                .collect::<Vec<_>>(),
+        )?),
+        DeviceCommand::ResolveIdConflicts => match session
+            .device_manager(DeviceManagerRequest::resolve_id_conflicts {})
+            .await?
+        {
+            DeviceManagerResponse::resolve_id_conflicts(reassignments) => {
+                print_json(&serde_json::to_value(reassignments)?)
+            }
+            _ => anyhow::bail!("Unexpected response from device manager"),
+        },
+        DeviceCommand::Watch { seconds } => {
+            let mut events = session.events.lock().await;
+            let watch = async {
+                loop {
+                    match events.recv().await {
+                        Ok(e) if e.provider == session.address => {
+                            println!("{}", serde_json::to_string(&e.event)?)
+                        }
+                        Ok(_) => (),
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            log::warn!("Missed {} events", n)
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+                anyhow::Ok(())
+            };
+
+            match seconds {
+                Some(seconds) => tokio::time::timeout(Duration::from_secs(seconds), watch)
+                    .await
+                    .unwrap_or(Ok(())),
+                None => tokio::select! {
+                  r = watch => r,
+                  _ = tokio::signal::ctrl_c() => Ok(()),
+                },
+            }
+        }
+        DeviceCommand::Blink { serial } => print_json(
+            &session
+                .grapple(serial, GrappleDeviceRequest::blink {})
+                .await?,
+        ),
+        DeviceCommand::SetId { serial, id } => print_json(
+            &session
+                .grapple(serial, GrappleDeviceRequest::set_id { id })
+                .await?,
+        ),
+        DeviceCommand::SetName { serial, name } => print_json(
+            &session
+                .grapple(serial, GrappleDeviceRequest::set_name { name })
+                .await?,
+        ),
+        DeviceCommand::Commit { serial } => print_json(
+            &session
+                .grapple(serial, GrappleDeviceRequest::commit_to_eeprom {})
+                .await?,
+        ),
+        DeviceCommand::Lasercan(cmd) => {
+            let (serial, req) = match cmd {
+                LaserCanCommand::Status { serial } => (serial, LaserCanRequest::status {}),
+                LaserCanCommand::SetRange { serial, mode } => (
+                    serial,
+                    LaserCanRequest::set_range {
+                        mode: match mode {
+                            RangingMode::Short => LaserCanRangingMode::Short,
+                            RangingMode::Long => LaserCanRangingMode::Long,
+                        },
+                    },
+                ),
+                LaserCanCommand::SetRoi { serial, x, y, w, h } => (
+                    serial,
+                    LaserCanRequest::set_roi {
+                        roi: LaserCanRoi {
+                            x: LaserCanRoiU4(x),
+                            y: LaserCanRoiU4(y),
+                            w: LaserCanRoiU4(w),
+                            h: LaserCanRoiU4(h),
+                        },
+                    },
+                ),
+                LaserCanCommand::SetTimingBudget { serial, budget_ms } => (
+                    serial,
+                    LaserCanRequest::set_timing_budget {
+                        budget: match budget_ms {
+                            20 => LaserCanTimingBudget::TB20ms,
+                            33 => LaserCanTimingBudget::TB33ms,
+                            50 => LaserCanTimingBudget::TB50ms,
+                            100 => LaserCanTimingBudget::TB100ms,
+                            x => anyhow::bail!("Invalid timing budget: {}ms", x),
+                        },
+                    },
+                ),
+            };
+            print_json(&session.call(serial, req).await?)
+        }
+        DeviceCommand::Mitocandria(cmd) => {
+            let (serial, req) = match cmd {
+                MitocandriaCommand::Status { serial } => (serial, MitocandriaRequest::status {}),
+                MitocandriaCommand::SetSwitchable {
+                    serial,
+                    channel,
+                    enabled,
+                } => (
+                    serial,
+                    MitocandriaRequest::set_switchable_channel {
+                        channel: MitocandriaSwitchableChannelRequest { channel, enabled },
+                    },
+                ),
+                MitocandriaCommand::SetAdjustable {
+                    serial,
+                    channel,
+                    voltage_mv,
+                } => (
+                    serial,
+                    MitocandriaRequest::set_adjustable_channel {
+                        channel: MitocandriaAdjustableChannelRequest {
+                            channel,
+                            voltage: voltage_mv,
+                        },
+                    },
+                ),
+                MitocandriaCommand::Calibrate { serial } => {
+                    (serial, MitocandriaRequest::calibrate_adjustable_channel {})
+                }
+            };
+            print_json(&session.call(serial, req).await?)
+        }
+        DeviceCommand::Flexican(cmd) => {
+            let (serial, req) = match cmd {
+                FlexiCanCommand::Status { serial } => (serial, FlexiCanRequest::status {}),
+                FlexiCanCommand::ChannelName { serial, channel } => {
+                    (serial, FlexiCanRequest::get_channel_name { channel })
+                }
+                FlexiCanCommand::StartBridge { serial, channel } => {
+                    (serial, FlexiCanRequest::start_bridge { channel })
+                }
+                FlexiCanCommand::StopBridge { serial, channel } => {
+                    (serial, FlexiCanRequest::stop_bridge { channel })
+                }
+            };
+            print_json(&session.call(serial, req).await?)
+        }
+        DeviceCommand::Call {
+            serial,
+            json,
+            stats,
+        } => {
+            let data: serde_json::Value = serde_json::from_str(&json)?;
+            match stats {
+                true => print_json(&serde_json::to_value(
+                    session.call_with_stats(serial, data).await?,
+                )?),
+                false => print_json(&session.call(serial, data).await?),
+            }
+        }
+        DeviceCommand::ExportConfig { path } => {
+            let format = config_format(&path);
+            match session
+                .device_manager(DeviceManagerRequest::export_config { path, format })
+                .await?
+            {
+                DeviceManagerResponse::export_config(snapshot) => {
+                    print_json(&serde_json::to_value(snapshot)?)
+                }
+                _ => anyhow::bail!("Unexpected response from device manager"),
+            }
+        }
+        DeviceCommand::ImportConfig { path, commit } => {
+            let format = config_format(&path);
+            match session
+                .device_manager(DeviceManagerRequest::import_config {
+                    path,
+                    format,
+                    commit,
+                })
+                .await?
+            {
+                DeviceManagerResponse::import_config(results) => {
+                    print_json(&serde_json::to_value(&results)?)?;
+                    if results.iter().any(|r| r.error.is_some()) {
+                        anyhow::bail!("Some devices failed to apply their configuration");
+                    }
+                    Ok(())
+                }
+                _ => anyhow::bail!("Unexpected response from device manager"),
+            }
+        }
+        DeviceCommand::UpgradeAll { model, path } => {
+            let model = match model {
+                Model::Lasercan => GrappleModelId::LaserCan,
+                Model::Mitocandria => GrappleModelId::MitoCANdria,
+                Model::Flexican => GrappleModelId::FlexiCAN,
+            };
+            let data = std::fs::read(&path)?;
+            session
+                .device_manager(DeviceManagerRequest::start_bulk_field_upgrade { model, data })
+                .await?;
+
+            loop {
+                tokio::time::sleep(Duration::from_millis(1000)).await;
+                let status = match session
+                    .device_manager(DeviceManagerRequest::bulk_field_upgrade_status {})
+                    .await?
+                {
+                    DeviceManagerResponse::bulk_field_upgrade_status(Some(status)) => status,
+                    _ => anyhow::bail!("Unexpected response from device manager"),
+                };
+
+                for device in status.devices.iter() {
+                    eprintln!("{}: {:?}", device.serial, device.state);
+                }
+
+                if !status.running {
+                    print_json(&serde_json::to_value(&status)?)?;
+                    if status
+                        .devices
+                        .iter()
+                        .any(|d| matches!(d.state, BulkUpgradeState::Failed { .. }))
+                    {
+                        anyhow::bail!("Some devices failed to upgrade");
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        DeviceCommand::BundleInfo { path } => {
+            let data = std::fs::read(&path)?;
+            match session
+                .device_manager(DeviceManagerRequest::inspect_firmware_bundle { data })
+                .await?
+            {
+                DeviceManagerResponse::inspect_firmware_bundle(info) => {
+                    print_json(&serde_json::to_value(info)?)
+                }
+                _ => anyhow::bail!("Unexpected response from device manager"),
+            }
+        }
+    }
+}
+
+async fn run_local(manager: &ProviderManager, command: LocalCommand) -> anyhow::Result<()> {
+    match command {
+        LocalCommand::Providers => {
+            // Give USB detection a chance to run before listing.
+            tokio::time::sleep(Duration::from_millis(600)).await;
+            match manager
+                .rpc_process(ProviderManagerRequest::providers {})
+                .await?
+            {
+                ProviderManagerResponse::providers(providers) => {
+                    print_json(&serde_json::to_value(providers)?)
+                }
+                _ => anyhow::bail!("Unexpected response from provider manager"),
+            }
+        }
+        LocalCommand::ImportFirmware { path } => match manager
+            .rpc_process(ProviderManagerRequest::import_firmware_bundles { path })
+            .await?
+        {
+            ProviderManagerResponse::import_firmware_bundles(results) => {
+                print_json(&serde_json::to_value(&results)?)?;
+                if results.iter().any(|r| r.error.is_some()) {
+                    anyhow::bail!("Some bundles could not be imported");
+                }
+                Ok(())
+            }
+            _ => anyhow::bail!("Unexpected response from provider manager"),
+        },
+        LocalCommand::UpdateSource {
+            endpoint,
+            channel,
+            proxy,
+        } => {
+            let mut config = updates::update_source();
+            if endpoint.is_some() || channel.is_some() || proxy.is_some() {
+                if let Some(endpoint) = endpoint {
+                    config.endpoint = endpoint;
+                }
+                if let Some(channel) = channel {
+                    config.channel = match channel {
+                        Channel::Stable => UpdateChannel::Stable,
+                        Channel::Prerelease => UpdateChannel::Prerelease,
+                    };
+                }
+                if let Some(proxy) = proxy {
+                    config.proxy = Some(proxy).filter(|p| !p.is_empty());
+                }
+                updates::set_update_source(config.clone())?;
+            }
+            print_json(&serde_json::to_value(config)?)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    Builder::new().filter_level(log::LevelFilter::Warn).init();
+
+    let cli = Cli::parse();
+    let manager = ProviderManager::new().await;
+
+    let command = match cli.command {
+        Command::Local(command) => return run_local(&manager, command).await,
+        Command::Device(command) => command,
+    };
+
+    wait_for_provider(&manager, &cli.provider).await?;
+
+    let mut session = Session {
+        events: Mutex::new(manager.subscribe()),
+        manager,
+        address: cli.provider.clone(),
+        retry: None,
+    };
+
+    if cli.no_daemon {
+        session
+            .provider(WrappedDeviceProviderRequest::call {
+                req: serde_json::to_value(RoboRioDaemonRequest::set_use_daemon {
+                    use_daemon: false,
+                })?,
+            })
+            .await?;
+    }
+
+    // Start from the provider's policy, so options that aren't given keep their usual values
+    if cli.timeout_ms.is_some() || cli.attempts.is_some() {
+        let mut policy = match session
+            .device_manager(DeviceManagerRequest::retry_policy {})
+            .await?
+        {
+            DeviceManagerResponse::retry_policy(policy) => policy,
+            _ => anyhow::bail!("Unexpected response from device manager"),
+        };
+        if let Some(timeout_ms) = cli.timeout_ms {
+            policy.timeout_ms = timeout_ms;
+        }
+        if let Some(attempts) = cli.attempts {
+            policy.attempts = attempts;
+        }
+        policy.validate()?;
+        session.retry = Some(policy);
+    }
+
+    session
+        .provider(WrappedDeviceProviderRequest::connect {})
+        .await?;
+    tokio::time::sleep(Duration::from_millis(cli.wait_ms)).await;
+
+    let result = run(&session, command).await;
+
+    session
+        .provider(WrappedDeviceProviderRequest::disconnect {})
+        .await?;
+
+    // Wait for the provider to shut down, which for the RoboRIO includes reverting to robot code.
+    for _ in 0..100 {
+        if !session.info().await?.connected {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    result
+}
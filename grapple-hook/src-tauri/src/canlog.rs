@@ -1,11 +1,14 @@
 use std::{
     collections::VecDeque,
+    fs::File,
+    io::{BufWriter, Write},
     sync::atomic::{AtomicBool, AtomicUsize},
-    time::Instant,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use crate::rpc::RpcBase;
 use grapple_frc_msgs::{
+    binmarshal::{LengthTaggedPayload, LengthTaggedPayloadOwned},
     bridge::BridgedCANMessage,
     grapple::{GrappleDeviceMessage, GrappleMessageId},
     MessageId,
@@ -13,9 +16,13 @@ use grapple_frc_msgs::{
 use grapple_hook_macros::rpc;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc, RwLock};
+use tokio::{
+    sync::{mpsc, Mutex, RwLock},
+    task::JoinHandle,
+};
 
 use bounded_static::{ToBoundedStatic, ToStatic};
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, Serialize, JsonSchema, ToStatic)]
 pub struct MailboxItem<'a> {
@@ -62,6 +69,329 @@ impl Filter {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub enum RecordingFormat {
+    /// Linux `candump -L` text format
+    Candump,
+    /// PCAP with the SocketCAN link type, for Wireshark
+    Pcap,
+    /// JSON lines of MailboxItem, including the decoded Grapple message
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecordingStatus {
+    pub path: String,
+    pub format: RecordingFormat,
+    pub messages: usize,
+}
+
+const PCAP_LINKTYPE_CAN_SOCKETCAN: u32 = 227;
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CANFD_FDF: u8 = 0x04;
+
+pub struct CanLogRecorder {
+    writer: BufWriter<File>,
+    status: RecordingStatus,
+}
+
+impl CanLogRecorder {
+    pub fn create(path: String, format: RecordingFormat) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(File::create(&path)?);
+
+        if let RecordingFormat::Pcap = format {
+            writer.write_all(&0xa1b2c3d4u32.to_le_bytes())?;
+            writer.write_all(&2u16.to_le_bytes())?;
+            writer.write_all(&4u16.to_le_bytes())?;
+            writer.write_all(&0i32.to_le_bytes())?;
+            writer.write_all(&0u32.to_le_bytes())?;
+            writer.write_all(&65535u32.to_le_bytes())?;
+            writer.write_all(&PCAP_LINKTYPE_CAN_SOCKETCAN.to_le_bytes())?;
+        }
+
+        Ok(Self {
+            writer,
+            status: RecordingStatus {
+                path,
+                format,
+                messages: 0,
+            },
+        })
+    }
+
+    /// Write a message, as received at the given time
+    pub fn write(&mut self, time: SystemTime, item: &MailboxItem<'_>) -> anyhow::Result<()> {
+        let now = time.duration_since(UNIX_EPOCH)?;
+        let id: u32 = item.raw.id.into();
+        let data = &item.raw.data.0[..];
+
+        match self.status.format {
+            RecordingFormat::Candump => {
+                let hex = data
+                    .iter()
+                    .map(|b| format!("{:02X}", b))
+                    .collect::<String>();
+                // Anything longer than a classic frame can only be represented as CAN FD
+                let sep = if data.len() > 8 {
+                    format!("##{:X}", CANFD_FDF)
+                } else {
+                    "#".to_owned()
+                };
+                writeln!(
+                    self.writer,
+                    "({}.{:06}) can0 {:08X}{}{}",
+                    now.as_secs(),
+                    now.subsec_micros(),
+                    id,
+                    sep,
+                    hex
+                )?;
+            }
+            RecordingFormat::Pcap => {
+                let data = &data[..data.len().min(64)];
+                let (frame_len, flags) = if data.len() > 8 {
+                    (64, CANFD_FDF)
+                } else {
+                    (8, 0)
+                };
+
+                let mut frame = vec![0u8; 8 + frame_len];
+                frame[0..4].copy_from_slice(&(id | CAN_EFF_FLAG).to_be_bytes());
+                frame[4] = data.len() as u8;
+                frame[5] = flags;
+                frame[8..8 + data.len()].copy_from_slice(data);
+
+                self.writer
+                    .write_all(&(now.as_secs() as u32).to_le_bytes())?;
+                self.writer.write_all(&now.subsec_micros().to_le_bytes())?;
+                self.writer.write_all(&(frame.len() as u32).to_le_bytes())?;
+                self.writer.write_all(&(frame.len() as u32).to_le_bytes())?;
+                self.writer.write_all(&frame)?;
+            }
+            RecordingFormat::Json => {
+                serde_json::to_writer(&mut self.writer, item)?;
+                writeln!(self.writer)?;
+            }
+        }
+
+        self.status.messages += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<RecordingStatus> {
+        self.writer.flush()?;
+        Ok(self.status)
+    }
+}
+
+/// A recording in progress. Messages are handed over to a blocking task to be written, so the
+/// provider's message loop never waits on the disk.
+struct ActiveRecording {
+    tx: mpsc::UnboundedSender<(SystemTime, MailboxItem<'static>)>,
+    status: RecordingStatus,
+    writer: JoinHandle<anyhow::Result<RecordingStatus>>,
+}
+
+impl ActiveRecording {
+    async fn start(path: String, format: RecordingFormat) -> anyhow::Result<Self> {
+        let mut recorder =
+            tokio::task::spawn_blocking(move || CanLogRecorder::create(path, format)).await??;
+        let status = recorder.status.clone();
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<(SystemTime, MailboxItem<'static>)>();
+        let writer = tokio::task::spawn_blocking(move || {
+            while let Some((time, item)) = rx.blocking_recv() {
+                recorder.write(time, &item)?;
+            }
+            recorder.finish()
+        });
+
+        Ok(Self { tx, status, writer })
+    }
+
+    /// Write out everything still queued and close the file
+    async fn finish(self) -> anyhow::Result<RecordingStatus> {
+        drop(self.tx);
+        self.writer.await?
+    }
+}
+
+/// A message read back from a recording, with its offset from the start of the recording.
+pub type RecordedMessage = (Duration, BridgedCANMessage<'static>);
+
+fn recorded_message(offset: Duration, id: u32, data: Vec<u8>) -> RecordedMessage {
+    (
+        offset,
+        BridgedCANMessage {
+            id: (id & !CAN_EFF_FLAG).into(),
+            timestamp: offset.as_millis() as u32,
+            data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(data)).into(),
+        },
+    )
+}
+
+/// Parse a candump `seconds.fraction` timestamp exactly, which an f64 of seconds since the epoch
+/// isn't to the microsecond
+fn parse_candump_timestamp(ts: &str) -> Option<Duration> {
+    let (secs, frac) = ts.split_once('.').unwrap_or((ts, "0"));
+    let nanos = format!("{:0<9}", frac).get(..9)?.parse().ok()?;
+    Some(Duration::new(secs.parse().ok()?, nanos))
+}
+
+fn read_candump(content: &[u8]) -> anyhow::Result<Vec<RecordedMessage>> {
+    let mut messages = vec![];
+    let mut start: Option<Duration> = None;
+
+    for line in std::str::from_utf8(content)?.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = || anyhow::anyhow!("Invalid candump line: {}", line);
+        let mut parts = line.split_whitespace();
+        let ts = parts
+            .next()
+            .and_then(|x| x.strip_prefix('('))
+            .and_then(|x| x.strip_suffix(')'))
+            .and_then(parse_candump_timestamp)
+            .ok_or_else(invalid)?;
+        let frame = parts.nth(1).ok_or_else(invalid)?;
+
+        let (id, data) = frame.split_once('#').ok_or_else(invalid)?;
+        // CAN FD frames are ID##<flags><data>
+        let data = match data.strip_prefix('#') {
+            Some(fd) => fd.get(1..).ok_or_else(invalid)?,
+            None => data,
+        };
+
+        let id = u32::from_str_radix(id, 16)?;
+        if data.len() % 2 != 0 || !data.is_ascii() {
+            return Err(invalid());
+        }
+        let data = (0..data.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&data[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        let start = *start.get_or_insert(ts);
+        messages.push(recorded_message(ts.saturating_sub(start), id, data));
+    }
+
+    Ok(messages)
+}
+
+fn read_pcap(content: &[u8]) -> anyhow::Result<Vec<RecordedMessage>> {
+    if content.len() < 24 {
+        anyhow::bail!("Not a PCAP file");
+    }
+
+    let magic = [content[0], content[1], content[2], content[3]];
+    let (le, nanos) = match u32::from_le_bytes(magic) {
+        0xa1b2c3d4 => (true, false),
+        0xa1b23c4d => (true, true),
+        _ => match u32::from_be_bytes(magic) {
+            0xa1b2c3d4 => (false, false),
+            0xa1b23c4d => (false, true),
+            _ => anyhow::bail!("Not a PCAP file"),
+        },
+    };
+
+    let read_u32 = |b: &[u8]| {
+        let b = [b[0], b[1], b[2], b[3]];
+        if le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        }
+    };
+
+    if read_u32(&content[20..24]) != PCAP_LINKTYPE_CAN_SOCKETCAN {
+        anyhow::bail!("PCAP file is not a SocketCAN capture");
+    }
+
+    let mut messages = vec![];
+    let mut start: Option<Duration> = None;
+    let mut offset = 24;
+
+    while offset + 16 <= content.len() {
+        let ts_sec = read_u32(&content[offset..]);
+        let ts_frac = read_u32(&content[offset + 4..]);
+        let incl_len = read_u32(&content[offset + 8..]) as usize;
+        offset += 16;
+
+        let frame = content
+            .get(offset..offset + incl_len)
+            .ok_or(anyhow::anyhow!("Truncated PCAP file"))?;
+        offset += incl_len;
+
+        if frame.len() < 8 {
+            continue;
+        }
+
+        let ts = Duration::new(ts_sec as u64, if nanos { ts_frac } else { ts_frac * 1000 });
+        let start = *start.get_or_insert(ts);
+
+        // The SocketCAN header is always big-endian, regardless of the file's byte order.
+        let id = u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]);
+        let len = (frame[4] as usize).min(frame.len() - 8);
+
+        messages.push(recorded_message(
+            ts.saturating_sub(start),
+            id,
+            frame[8..8 + len].to_vec(),
+        ));
+    }
+
+    Ok(messages)
+}
+
+fn read_json(content: &[u8]) -> anyhow::Result<Vec<RecordedMessage>> {
+    #[derive(Deserialize)]
+    struct RawItem {
+        id: MessageId,
+        timestamp: u32,
+        data: Vec<u8>,
+    }
+
+    #[derive(Deserialize)]
+    struct Item {
+        raw: RawItem,
+    }
+
+    let mut messages = vec![];
+    let mut start: Option<u32> = None;
+
+    for line in std::str::from_utf8(content)?.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let item: Item = serde_json::from_str(line)?;
+        let start = *start.get_or_insert(item.raw.timestamp);
+        messages.push(recorded_message(
+            Duration::from_millis(item.raw.timestamp.saturating_sub(start) as u64),
+            item.raw.id.into(),
+            item.raw.data,
+        ));
+    }
+
+    Ok(messages)
+}
+
+/// Read back a recording made by [CanLogRecorder].
+pub fn read_recording(
+    path: &str,
+    format: &RecordingFormat,
+) -> anyhow::Result<Vec<RecordedMessage>> {
+    let content = std::fs::read(path)?;
+    match format {
+        RecordingFormat::Candump => read_candump(&content),
+        RecordingFormat::Pcap => read_pcap(&content),
+        RecordingFormat::Json => read_json(&content),
+    }
+}
+
 pub struct CanLog {
     logging_enabled: AtomicBool,
     max_mailbox_size: usize,
@@ -70,6 +400,7 @@ pub struct CanLog {
     filters: RwLock<Vec<Filter>>,
     can_send_raw_tx: mpsc::Sender<(MessageId, Vec<u8>)>,
     rel_epoch: Instant,
+    recorder: Mutex<Option<ActiveRecording>>,
 }
 
 impl CanLog {
@@ -82,6 +413,7 @@ impl CanLog {
             filters: RwLock::new(Vec::new()),
             can_send_raw_tx,
             rel_epoch: Instant::now(),
+            recorder: Mutex::new(None),
         }
     }
 
@@ -107,10 +439,12 @@ impl CanLog {
 
         let seq = self.seq.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
-        if self
+        let mut recorder = self.recorder.lock().await;
+        let logging_enabled = self
             .logging_enabled
-            .load(std::sync::atomic::Ordering::Relaxed)
-        {
+            .load(std::sync::atomic::Ordering::Relaxed);
+
+        if logging_enabled || recorder.is_some() {
             let item = MailboxItem {
                 seq,
                 raw: BridgedCANMessage {
@@ -121,12 +455,30 @@ impl CanLog {
                 grpl_defrag: defrag.map(ToBoundedStatic::to_static),
             };
 
-            let mut q = self.mailbox.write().await;
-            while q.len() >= (self.max_mailbox_size - 1) {
-                q.pop_front();
+            if let Some(rec) = recorder.as_mut() {
+                match rec.tx.send((SystemTime::now(), item.clone())) {
+                    Ok(()) => rec.status.messages += 1,
+                    // The writer only goes away if it failed
+                    Err(_) => {
+                        if let Some(rec) = recorder.take() {
+                            tokio::spawn(async move {
+                                if let Err(e) = rec.finish().await {
+                                    log::warn!("Could not write to CAN recording, stopping: {}", e);
+                                }
+                            });
+                        }
+                    }
+                }
             }
 
-            q.push_back(item);
+            if logging_enabled {
+                let mut q = self.mailbox.write().await;
+                while q.len() >= (self.max_mailbox_size - 1) {
+                    q.pop_front();
+                }
+
+                q.push_back(item);
+            }
         }
     }
 }
@@ -157,4 +509,31 @@ impl CanLog {
         self.can_send_raw_tx.send((id, data)).await?;
         Ok(())
     }
+
+    async fn start_recording(&self, path: String, format: RecordingFormat) -> anyhow::Result<()> {
+        let old = self.recorder.lock().await.take();
+        if let Some(old) = old {
+            old.finish().await?;
+        }
+        let rec = ActiveRecording::start(path, format).await?;
+        *self.recorder.lock().await = Some(rec);
+        Ok(())
+    }
+
+    async fn stop_recording(&self) -> anyhow::Result<Option<RecordingStatus>> {
+        let rec = self.recorder.lock().await.take();
+        match rec {
+            Some(rec) => Ok(Some(rec.finish().await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn recording_status(&self) -> anyhow::Result<Option<RecordingStatus>> {
+        Ok(self
+            .recorder
+            .lock()
+            .await
+            .as_ref()
+            .map(|x| x.status.clone()))
+    }
 }
@@ -0,0 +1,221 @@
+use std::{sync::Arc, time::Duration};
+
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, RwLock};
+
+use super::{
+    device_manager::{lookup_info, DeviceId, DeviceStore, Domain},
+    field_upgrade_worker,
+    firmware_bundle::normalise_version,
+    start_field_upgrade, FirmwareUpgradeError, SendWrapper,
+};
+
+const ENTER_DFU_TIMEOUT: Duration = Duration::from_secs(10);
+const REBOOT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "state", content = "data")]
+pub enum BulkUpgradeState {
+    Pending,
+    EnteringDfu,
+    Flashing { progress: f64 },
+    Rebooting,
+    Success { version: Option<String> },
+    Failed { error: FirmwareUpgradeError },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BulkUpgradeDeviceStatus {
+    pub domain: Domain,
+    pub serial: u32,
+    pub state: BulkUpgradeState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BulkUpgradeStatus {
+    pub model: GrappleModelId,
+    pub running: bool,
+    pub devices: Vec<BulkUpgradeDeviceStatus>,
+}
+
+/// Everything about a bulk upgrade that's the same for each device in it.
+struct UpgradeContext<'a> {
+    devices: &'a DeviceStore,
+    sender: &'a SendWrapper,
+    firmware: &'a Arc<Vec<u8>>,
+    expected_version: Option<&'a str>,
+    chunk_size: usize,
+}
+
+/// State shared between the DeviceManager and a running bulk upgrade.
+#[derive(Default)]
+pub struct BulkUpgrade {
+    pub status: RwLock<Option<BulkUpgradeStatus>>,
+    /// The device currently being flashed, so V1 part acks can be routed to the worker.
+    pub flashing: RwLock<Option<(Domain, u8)>>,
+    pub ack: Arc<Notify>,
+}
+
+impl BulkUpgrade {
+    pub async fn is_running(&self) -> bool {
+        self.status
+            .read()
+            .await
+            .as_ref()
+            .map(|x| x.running)
+            .unwrap_or(false)
+    }
+
+    async fn set_state(&self, idx: usize, state: BulkUpgradeState) {
+        if let Some(status) = self.status.write().await.as_mut() {
+            status.devices[idx].state = state;
+        }
+    }
+
+    pub async fn run(
+        self: Arc<Self>,
+        devices: Arc<DeviceStore>,
+        senders: Vec<(Domain, SendWrapper)>,
+        firmware: Arc<Vec<u8>>,
+        expected_version: Option<String>,
+        chunk_size: usize,
+    ) {
+        let targets: Vec<(Domain, u32)> = match self.status.read().await.as_ref() {
+            Some(status) => status
+                .devices
+                .iter()
+                .map(|d| (d.domain.clone(), d.serial))
+                .collect(),
+            None => vec![],
+        };
+
+        for (idx, (domain, serial)) in targets.into_iter().enumerate() {
+            let result = match senders.iter().find(|(d, _)| *d == domain) {
+                Some((_, sender)) => {
+                    let ctx = UpgradeContext {
+                        devices: &devices,
+                        sender,
+                        firmware: &firmware,
+                        expected_version: expected_version.as_deref(),
+                        chunk_size,
+                    };
+                    self.upgrade_one(idx, &ctx, &domain, serial).await
+                }
+                None => Err(anyhow::anyhow!("No connection to {}", domain)),
+            };
+            *self.flashing.write().await = None;
+
+            match result {
+                Ok(version) => {
+                    info!("Bulk upgrade of {} succeeded", serial);
+                    self.set_state(idx, BulkUpgradeState::Success { version })
+                        .await
+                }
+                Err(e) => {
+                    warn!("Bulk upgrade of {} failed: {}", serial, e);
+                    self.set_state(
+                        idx,
+                        BulkUpgradeState::Failed {
+                            error: FirmwareUpgradeError::from_error(&e),
+                        },
+                    )
+                    .await
+                }
+            }
+        }
+
+        if let Some(status) = self.status.write().await.as_mut() {
+            status.running = false;
+        }
+    }
+
+    async fn wait_for(
+        devices: &DeviceStore,
+        domain: &Domain,
+        id: &DeviceId,
+        timeout: Duration,
+    ) -> anyhow::Result<super::DeviceInfo> {
+        let start = std::time::Instant::now();
+        loop {
+            if let Some(info) = lookup_info(devices, domain, id).await {
+                return Ok(info);
+            }
+            if start.elapsed() > timeout {
+                anyhow::bail!("Timed out waiting for device {:?}", id);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    async fn upgrade_one(
+        &self,
+        idx: usize,
+        ctx: &UpgradeContext<'_>,
+        domain: &Domain,
+        serial: u32,
+    ) -> anyhow::Result<Option<String>> {
+        let UpgradeContext {
+            devices,
+            sender,
+            firmware,
+            expected_version,
+            chunk_size,
+        } = *ctx;
+        self.set_state(idx, BulkUpgradeState::EnteringDfu).await;
+
+        let dfu_id = DeviceId::Dfu(serial);
+        if lookup_info(devices, domain, &dfu_id).await.is_none() {
+            start_field_upgrade(sender, serial).await?;
+        }
+        let info = Self::wait_for(devices, domain, &dfu_id, ENTER_DFU_TIMEOUT).await?;
+        let device_id = info.require_device_id()?;
+
+        *self.flashing.write().await = Some((domain.clone(), device_id));
+        self.set_state(idx, BulkUpgradeState::Flashing { progress: 0.0 })
+            .await;
+
+        let progress = Arc::new(RwLock::new(None));
+        let worker = field_upgrade_worker(
+            sender.clone(),
+            device_id,
+            serial,
+            firmware.clone(),
+            info.is_dfu_in_progress,
+            progress.clone(),
+            self.ack.clone(),
+            chunk_size,
+        );
+        tokio::pin!(worker);
+
+        let mut interval = tokio::time::interval(Duration::from_millis(250));
+        loop {
+            tokio::select! {
+              r = &mut worker => { r?; break; },
+              _ = interval.tick() => {
+                let progress = progress.read().await.unwrap_or(0.0);
+                self.set_state(idx, BulkUpgradeState::Flashing { progress }).await;
+              }
+            }
+        }
+
+        self.set_state(idx, BulkUpgradeState::Rebooting).await;
+        let info =
+            Self::wait_for(devices, domain, &DeviceId::Serial(serial), REBOOT_TIMEOUT).await?;
+
+        // Make sure the device actually booted into the firmware we just sent it
+        if let Some(expected) = expected_version {
+            let expected = normalise_version(expected);
+            match info.firmware_version.as_deref().map(normalise_version) {
+                Some(actual) if actual == expected => (),
+                actual => anyhow::bail!(
+                    "Device is running firmware {} after the upgrade, expected {}",
+                    actual.unwrap_or("(unknown)"),
+                    expected
+                ),
+            }
+        }
+        Ok(info.firmware_version)
+    }
+}
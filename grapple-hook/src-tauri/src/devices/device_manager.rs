@@ -1,11 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::str;
 use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant};
 
+use chrono::Utc;
 use grapple_frc_msgs::grapple::{
     device_info::{GrappleDeviceInfo, GrappleModelId},
+    firmware::GrappleFirmwareMessage,
     GrappleBroadcastMessage, GrappleDeviceMessage,
 };
 use grapple_frc_msgs::grapple::{GrappleMessageId, TaggedGrappleMessage};
@@ -14,14 +17,23 @@ use grapple_frc_msgs::DEVICE_ID_BROADCAST;
 use grapple_hook_macros::rpc;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc, oneshot, RwLock};
-use uuid::Uuid;
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex, OwnedMutexGuard, RwLock};
 
+use super::bulk_upgrade::{
+    BulkUpgrade, BulkUpgradeDeviceStatus, BulkUpgradeState, BulkUpgradeStatus,
+};
+use super::events::{status_frame_model, DeviceEvent, EVENT_CAPACITY};
+use super::firmware_bundle::{FirmwareBundle, FirmwareBundleInfo};
 use super::flexican::FlexiCan;
 use super::lasercan::LaserCan;
 use super::mitocandria::Mitocandria;
+use super::retry::{with_request_scope, RequestStats, RetryPolicy};
+use super::snapshot::{ConfigApplyResult, ConfigFormat, ConfigSnapshot, DeviceSnapshot};
 // use super::powerful_panda::PowerfulPanda;
-use super::{DeviceInfo, DeviceType, FirmwareUpgradeDevice, RootDevice, VersionGatedDevice};
+use super::{
+    prepare_firmware, DeviceInfo, DeviceType, FirmwareUpgradeDevice, GrappleDevice, RootDevice,
+    SendWrapper, UnknownGrappleDevice, VersionGatedDevice,
+};
 // use super::{DeviceInfo, spiderlan::SpiderLAN};
 use crate::rpc::RpcBase;
 
@@ -33,41 +45,336 @@ pub enum DeviceId {
 
 pub type Domain = String;
 
+struct Liveness {
+    last_seen: Instant,
+    online: bool,
+}
+
 pub struct DeviceEntry {
     device: Box<dyn RootDevice + Send + Sync>,
     info: Arc<RwLock<DeviceInfo>>,
-    last_seen: std::time::Instant,
+    liveness: std::sync::Mutex<Liveness>,
+}
+
+impl DeviceEntry {
+    fn new(device: Box<dyn RootDevice + Send + Sync>, info: Arc<RwLock<DeviceInfo>>) -> Self {
+        Self {
+            device,
+            info,
+            liveness: std::sync::Mutex::new(Liveness {
+                last_seen: Instant::now(),
+                online: true,
+            }),
+        }
+    }
+
+    /// Record that the device has just answered enumeration. Returns true if it had been offline.
+    fn seen(&self) -> bool {
+        let mut liveness = self.liveness.lock().unwrap();
+        liveness.last_seen = Instant::now();
+        !std::mem::replace(&mut liveness.online, true)
+    }
+
+    fn presence(&self) -> DevicePresence {
+        let liveness = self.liveness.lock().unwrap();
+        let since = chrono::Duration::from_std(liveness.last_seen.elapsed()).unwrap_or_default();
+        DevicePresence {
+            online: liveness.online,
+            last_seen: (Utc::now() - since).to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DevicePresence {
+    /// False once the device has stopped answering enumeration requests
+    pub online: bool,
+    /// RFC3339 timestamp of the last time the device answered enumeration
+    pub last_seen: String,
+}
+
+/// How long a device can go without answering enumeration requests before it's considered
+/// offline, and then forgotten altogether.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct AgeOffConfig {
+    pub offline_after_ms: u64,
+    /// Offline devices are kept until the provider is reset if this isn't set
+    pub forget_after_ms: Option<u64>,
+}
+
+impl Default for AgeOffConfig {
+    fn default() -> Self {
+        Self {
+            offline_after_ms: 4000,
+            forget_after_ms: None,
+        }
+    }
 }
 
-pub type RepliesWaiting =
-    Arc<RwLock<HashMap<u32, HashMap<Uuid, oneshot::Sender<TaggedGrappleMessage<'static>>>>>>;
+type DeviceMap = HashMap<Domain, HashMap<DeviceId, Arc<DeviceEntry>>>;
+
+/// A copy of a domain's entries, so the devices can be used without holding the map lock
+pub type DeviceEntries = Vec<(DeviceId, Arc<DeviceEntry>)>;
+
+/// The devices on each domain. The map is only ever locked for long enough to look up, add or
+/// remove entries, never while waiting on a device - callers take a copy of the entries they need
+/// and talk to the devices after the lock is released. The lock is synchronous so that holding it
+/// across an `.await` is a compile error rather than a stalled enumeration.
+pub struct DeviceStore(std::sync::RwLock<DeviceMap>);
+
+impl DeviceStore {
+    fn new<'a>(domains: impl Iterator<Item = &'a Domain>) -> Self {
+        Self(std::sync::RwLock::new(
+            domains
+                .map(|domain| (domain.clone(), HashMap::new()))
+                .collect(),
+        ))
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, DeviceMap> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, DeviceMap> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn get(&self, domain: &Domain, id: &DeviceId) -> Option<Arc<DeviceEntry>> {
+        self.read().get(domain)?.get(id).cloned()
+    }
+
+    /// Find a device by ID on any domain
+    pub fn find(&self, id: &DeviceId) -> Option<(Domain, Arc<DeviceEntry>)> {
+        self.read().iter().find_map(|(domain, devices)| {
+            devices
+                .get(id)
+                .map(|device| (domain.clone(), device.clone()))
+        })
+    }
+
+    pub fn domain(&self, domain: &Domain) -> DeviceEntries {
+        self.read()
+            .get(domain)
+            .map(|devices| {
+                devices
+                    .iter()
+                    .map(|(id, device)| (id.clone(), device.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn all(&self) -> Vec<(Domain, DeviceEntries)> {
+        self.read()
+            .iter()
+            .map(|(domain, devices)| {
+                (
+                    domain.clone(),
+                    devices
+                        .iter()
+                        .map(|(id, device)| (id.clone(), device.clone()))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Requests waiting on a reply from a device, keyed by the ID of the reply. Replies carry nothing
+/// else that ties them to the request that caused them, so only one request per reply ID can be in
+/// flight at a time - concurrent requests of the same class to the same device queue up behind
+/// each other instead of all taking the first reply.
+#[derive(Default)]
+pub struct PendingRequests {
+    waiting: RwLock<HashMap<u32, oneshot::Sender<TaggedGrappleMessage<'static>>>>,
+    queues: std::sync::Mutex<HashMap<u32, Arc<Mutex<()>>>>,
+}
+
+/// A request's turn to wait on its reply ID, which lasts until it's dropped.
+pub(super) struct QueueTurn<'a> {
+    pending: &'a PendingRequests,
+    reply_id: u32,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for QueueTurn<'_> {
+    fn drop(&mut self) {
+        self.guard.take();
+        // Anyone else waiting for a turn holds a reference to the queue, so if the map's is the
+        // only one left the queue is empty and can go.
+        let mut queues = self.pending.queues.lock().unwrap();
+        if queues
+            .get(&self.reply_id)
+            .is_some_and(|queue| Arc::strong_count(queue) == 1)
+        {
+            queues.remove(&self.reply_id);
+        }
+    }
+}
+
+impl PendingRequests {
+    /// Wait for our turn to send a request answered by the given reply ID.
+    pub(super) async fn queue(&self, reply_id: u32) -> QueueTurn<'_> {
+        let queue = self
+            .queues
+            .lock()
+            .unwrap()
+            .entry(reply_id)
+            .or_default()
+            .clone();
+        QueueTurn {
+            pending: self,
+            reply_id,
+            guard: Some(queue.lock_owned().await),
+        }
+    }
+
+    pub(super) async fn wait_for(
+        &self,
+        reply_id: u32,
+    ) -> oneshot::Receiver<TaggedGrappleMessage<'static>> {
+        let (tx, rx) = oneshot::channel();
+        if self.waiting.write().await.insert(reply_id, tx).is_some() {
+            warn!(
+                "Request for reply {:x} replaced one already in flight",
+                reply_id
+            );
+        }
+        rx
+    }
+
+    pub(super) async fn cancel(&self, reply_id: u32) {
+        self.waiting.write().await.remove(&reply_id);
+    }
+
+    async fn reply(&self, reply_id: u32, msg: &TaggedGrappleMessage<'static>) {
+        if let Some(tx) = self.waiting.write().await.remove(&reply_id) {
+            tx.send(msg.clone()).ok(); // ok since it's fine if the channel is closed, e.g. timeouts.
+        }
+    }
+}
+
+pub type RepliesWaiting = Arc<PendingRequests>;
 
 pub struct DeviceManager {
     send: HashMap<Domain, mpsc::Sender<TaggedGrappleMessage<'static>>>,
     replies_waiting: HashMap<Domain, RepliesWaiting>,
-    devices: RwLock<HashMap<Domain, HashMap<DeviceId, DeviceEntry>>>,
+    devices: Arc<DeviceStore>,
+    bulk_upgrade: Arc<BulkUpgrade>,
+    age_off: RwLock<AgeOffConfig>,
+    retry: Arc<RwLock<RetryPolicy>>,
+    events: broadcast::Sender<DeviceEvent>,
+}
+
+pub(super) async fn lookup_info(
+    devices: &DeviceStore,
+    domain: &Domain,
+    id: &DeviceId,
+) -> Option<DeviceInfo> {
+    let info = devices.get(domain, id)?.info.clone();
+    let info = info.read().await.clone();
+    Some(info)
+}
+
+/// Find devices that share an ID with another device of the same model, which robot code can't
+/// tell apart. Maps the serial of each conflicting device to the serials of the others.
+async fn id_conflicts(devices: &[(DeviceId, Arc<DeviceEntry>)]) -> HashMap<u32, Vec<u32>> {
+    let mut by_id: HashMap<(u8, u8), Vec<u32>> = HashMap::new();
+    for (id, device) in devices.iter() {
+        // Devices in DFU don't answer to their ID
+        let DeviceId::Serial(serial) = id else {
+            continue;
+        };
+        if !device.liveness.lock().unwrap().online {
+            continue;
+        }
+        let info = device.info.read().await;
+        if let (DeviceType::Grapple(model), Some(device_id)) = (&info.device_type, info.device_id) {
+            by_id
+                .entry((model.clone() as u8, device_id))
+                .or_default()
+                .push(*serial);
+        }
+    }
+
+    let mut conflicts = HashMap::new();
+    for mut serials in by_id.into_values().filter(|s| s.len() > 1) {
+        serials.sort();
+        for serial in serials.iter() {
+            conflicts.insert(
+                *serial,
+                serials.iter().filter(|s| *s != serial).cloned().collect(),
+            );
+        }
+    }
+    conflicts
+}
+
+/// The result of an RPC call to a device, along with how hard it was to get
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CallReport {
+    pub result: serde_json::Value,
+    pub stats: RequestStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct IdReassignment {
+    pub domain: Domain,
+    pub serial: u32,
+    pub old_id: u8,
+    pub new_id: u8,
+}
+
+fn firmware_chunk_size(model: &GrappleModelId) -> usize {
+    match model {
+        GrappleModelId::LaserCan => 8,
+        _ => 64,
+    }
 }
 
 impl DeviceManager {
     pub fn new(send: HashMap<Domain, mpsc::Sender<TaggedGrappleMessage<'static>>>) -> Self {
-        let mut devices = HashMap::new();
         let mut replies_waiting = HashMap::new();
 
         for domain in send.keys() {
-            devices.insert(domain.clone(), HashMap::new());
-            replies_waiting.insert(domain.clone(), Arc::new(RwLock::new(HashMap::new())));
+            replies_waiting.insert(domain.clone(), Arc::new(PendingRequests::default()));
         }
 
         Self {
+            devices: Arc::new(DeviceStore::new(send.keys())),
             send,
-            devices: RwLock::new(devices),
             replies_waiting,
+            bulk_upgrade: Arc::new(BulkUpgrade::default()),
+            age_off: RwLock::new(AgeOffConfig::default()),
+            retry: Arc::new(RwLock::new(RetryPolicy::default())),
+            events: broadcast::channel(EVENT_CAPACITY).0,
         }
     }
 
+    pub fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: DeviceEvent) {
+        self.events.send(event).ok(); // ok since there may be nobody listening
+    }
+
+    fn sender(&self, domain: &Domain) -> SendWrapper {
+        SendWrapper(
+            self.send.get(domain).unwrap().clone(),
+            self.replies_waiting.get(domain).unwrap().clone(),
+            self.retry.clone(),
+        )
+    }
+
     pub async fn reset(&self) {
-        for (_, devices) in self.devices.write().await.iter_mut() {
-            devices.clear();
+        for (domain, devices) in self.devices.write().iter_mut() {
+            for (id, _) in devices.drain() {
+                self.emit(DeviceEvent::Removed {
+                    domain: domain.clone(),
+                    id,
+                });
+            }
         }
     }
 
@@ -77,82 +384,139 @@ impl DeviceManager {
             true => DeviceId::Dfu(info.serial.unwrap()),
         };
 
-        let now = std::time::Instant::now();
-
-        // try_write since long-running RPC calls (such as those waiting for a response)
-        // will deadlock until the timeout resolves.
-        if let Ok(mut dev_map) = self.devices.try_write() {
-            let devices = dev_map.get_mut(domain).unwrap();
+        if let Some(entry) = self.devices.get(domain, &id) {
+            let old = std::mem::replace(&mut *entry.info.write().await, info.clone());
+            if entry.seen() {
+                info!("Device {:?} on {} is back online", id, domain);
+                self.emit(DeviceEvent::PresenceChanged {
+                    domain: domain.clone(),
+                    id: id.clone(),
+                    online: true,
+                });
+                entry.device.back_online().await;
+            }
 
-            if !devices.contains_key(&id) {
-                let device_type = info.device_type.clone();
-                let info_arc = Arc::new(RwLock::new(info));
+            if old.device_id != info.device_id {
+                self.emit(DeviceEvent::IdChanged {
+                    domain: domain.clone(),
+                    id: id.clone(),
+                    old_id: old.device_id,
+                    new_id: info.device_id,
+                });
+            }
+            if old.name != info.name {
+                self.emit(DeviceEvent::NameChanged {
+                    domain: domain.clone(),
+                    id: id.clone(),
+                    old_name: old.name,
+                    new_name: info.name,
+                });
+            }
+            return Ok(());
+        }
 
-                let send = super::SendWrapper(
-                    self.send.get(domain).unwrap().clone(),
-                    self.replies_waiting.get(domain).unwrap().clone(),
+        // Some devices talk to the hardware while they're being set up, so this is done before
+        // taking the map lock.
+        let device_type = info.device_type.clone();
+        let info_arc = Arc::new(RwLock::new(info.clone()));
+
+        let send = self.sender(domain);
+
+        let device = match (&id, device_type) {
+            (DeviceId::Dfu(..), DeviceType::Grapple(GrappleModelId::LaserCan)) => Box::new(
+                FirmwareUpgradeDevice::<LaserCan>::new(
+                    send,
+                    info_arc.clone(),
+                    firmware_chunk_size(&GrappleModelId::LaserCan),
+                )
+                .resume_if_interrupted()
+                .await,
+            ),
+            (DeviceId::Serial(..), DeviceType::Grapple(GrappleModelId::LaserCan)) => {
+                LaserCan::maybe_gate(send, info_arc.clone(), LaserCan::new).await
+            }
+            (DeviceId::Dfu(..), DeviceType::Grapple(GrappleModelId::FlexiCAN)) => Box::new(
+                FirmwareUpgradeDevice::<FlexiCan>::new(
+                    send,
+                    info_arc.clone(),
+                    firmware_chunk_size(&GrappleModelId::FlexiCAN),
+                )
+                .resume_if_interrupted()
+                .await,
+            ),
+            (DeviceId::Serial(..), DeviceType::Grapple(GrappleModelId::FlexiCAN)) => {
+                FlexiCan::maybe_gate(send, info_arc.clone(), FlexiCan::new).await
+            }
+            (DeviceId::Dfu(..), DeviceType::Grapple(GrappleModelId::MitoCANdria)) => Box::new(
+                FirmwareUpgradeDevice::<Mitocandria>::new(
+                    send,
+                    info_arc.clone(),
+                    firmware_chunk_size(&GrappleModelId::MitoCANdria),
+                )
+                .resume_if_interrupted()
+                .await,
+            ),
+            (DeviceId::Serial(..), DeviceType::Grapple(GrappleModelId::MitoCANdria)) => {
+                Mitocandria::maybe_gate(send, info_arc.clone(), Mitocandria::new).await
+            }
+            (DeviceId::Dfu(..), DeviceType::Grapple(model)) => Box::new(
+                FirmwareUpgradeDevice::<UnknownGrappleDevice>::new(
+                    send,
+                    info_arc.clone(),
+                    firmware_chunk_size(&model),
+                )
+                .resume_if_interrupted()
+                .await,
+            ),
+            (DeviceId::Serial(..), DeviceType::Grapple(model)) => {
+                warn!(
+                    "Found a {:?}, which this version of GrappleHook doesn't support",
+                    model
                 );
+                Box::new(UnknownGrappleDevice::new(send, info_arc.clone()))
+            }
+            (_, device_type) => {
+                anyhow::bail!("Enumerated a non-Grapple device: {:?}", device_type)
+            }
+        };
+        let device_class = device.device_class().to_owned();
 
-                let device = match (&id, device_type) {
-                    (DeviceId::Dfu(..), DeviceType::Grapple(GrappleModelId::LaserCan)) => Box::new(
-                        FirmwareUpgradeDevice::<LaserCan>::new(send, info_arc.clone(), 8),
-                    ),
-                    (DeviceId::Serial(..), DeviceType::Grapple(GrappleModelId::LaserCan)) => {
-                        LaserCan::maybe_gate(send, info_arc.clone(), LaserCan::new).await
-                    }
-                    // (DeviceId::Dfu(..),     DeviceType::Grapple(GrappleModelId::FlexiCAN)) => Box::new(FirmwareUpgradeDevice::<FlexiCan>::new(send, info_arc.clone(), 64)),
-                    // (DeviceId::Serial(..),  DeviceType::Grapple(GrappleModelId::FlexiCAN)) => FlexiCan::maybe_gate(send, info_arc.clone(), FlexiCan::new).await,
-                    (DeviceId::Dfu(..), DeviceType::Grapple(GrappleModelId::MitoCANdria)) => {
-                        Box::new(FirmwareUpgradeDevice::<Mitocandria>::new(
-                            send,
-                            info_arc.clone(),
-                            64,
-                        ))
-                    }
-                    (DeviceId::Serial(..), DeviceType::Grapple(GrappleModelId::MitoCANdria)) => {
-                        Mitocandria::maybe_gate(send, info_arc.clone(), Mitocandria::new).await
-                    }
-                    _ => unreachable!(),
-                };
+        let previous = {
+            let mut dev_map = self.devices.write();
+            let devices = dev_map.get_mut(domain).unwrap();
+            if devices.contains_key(&id) {
+                // Another enumeration response for this device beat us to it
+                return Ok(());
+            }
+            devices.insert(id.clone(), Arc::new(DeviceEntry::new(device, info_arc)));
 
-                /* If a device has gone from Serial to DFU, or the reverse, remove the old one so it doesn't linger. */
-                match &id {
-                    DeviceId::Dfu(serial) => devices.remove(&DeviceId::Serial(*serial)),
-                    DeviceId::Serial(serial) => devices.remove(&DeviceId::Dfu(*serial)),
-                };
+            /* If a device has gone from Serial to DFU, or the reverse, remove the old one so it doesn't linger. */
+            match &id {
+                DeviceId::Dfu(serial) => devices.remove(&DeviceId::Serial(*serial)),
+                DeviceId::Serial(serial) => devices.remove(&DeviceId::Dfu(*serial)),
+            }
+        };
 
-                devices.insert(
-                    id,
-                    DeviceEntry {
-                        device,
-                        info: info_arc,
-                        last_seen: now,
-                    },
-                );
-            } else {
-                let deventry = devices.get_mut(&id).unwrap();
-                *deventry.info.write().await = info;
-                deventry.last_seen = now;
+        match (previous, &id) {
+            (Some(_), DeviceId::Dfu(serial) | DeviceId::Serial(serial)) => {
+                self.emit(DeviceEvent::DfuTransition {
+                    domain: domain.clone(),
+                    serial: *serial,
+                    is_dfu: info.is_dfu,
+                    device_class,
+                })
             }
+            (None, _) => self.emit(DeviceEvent::Added {
+                domain: domain.clone(),
+                id,
+                info,
+                device_class,
+            }),
         }
+
         Ok(())
     }
 
-    // async fn maybe_add_device(&self, domain: &String, id: &DeviceId, info: DeviceInfo, device: Box<dyn Device + Send + Sync>) -> anyhow::Result<()> {
-    //   let now = std::time::Instant::now();
-
-    //   let mut dev_map = self.devices.write().await;
-    //   let devices = dev_map.get_mut(domain).unwrap();
-    //   if !devices.contains_key(id) {
-    //     devices.insert(id.clone(), DeviceEntry { device, info: Arc::new(RwLock::new(info)), last_seen: now });
-    //   } else {
-    //     let deventry = devices.get_mut(id).unwrap();
-    //     *deventry.info.write().await = info;
-    //     deventry.last_seen = now;
-    //   }
-    //   Ok(())
-    // }
-
     pub async fn on_message(
         &self,
         domain: String,
@@ -161,13 +525,11 @@ impl DeviceManager {
     ) -> anyhow::Result<()> {
         let msg_id_u32: u32 = Into::<MessageId>::into(id).into();
 
-        let waiting = self.replies_waiting.get(&domain).unwrap();
-        if waiting.read().await.contains_key(&msg_id_u32) {
-            let mut w = waiting.write().await;
-            for (_, waiting_element) in w.remove(&msg_id_u32).unwrap() {
-                waiting_element.send(message.clone()).ok(); // ok since it's fine if the channel is closed, e.g. timeouts.
-            }
-        }
+        self.replies_waiting
+            .get(&domain)
+            .unwrap()
+            .reply(msg_id_u32, &message)
+            .await;
 
         match message.msg.clone() {
             GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(dinfo)) => {
@@ -197,14 +559,35 @@ impl DeviceManager {
                     _ => (),
                 }
             }
+            GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::UpdatePartAck) => {
+                if *self.bulk_upgrade.flashing.read().await
+                    == Some((domain.clone(), message.device_id))
+                {
+                    self.bulk_upgrade.ack.notify_one();
+                }
+            }
             _ => (),
         }
 
-        for (_, device) in self.devices.read().await.get(&domain).unwrap().iter() {
+        let status_model = status_frame_model(&message.msg);
+        for (id, device) in self.devices.domain(&domain) {
             match device.device.handle(message.clone()).await {
                 Ok(()) => (),
                 Err(e) => warn!("Error in message handler: {}", e),
             }
+
+            if let (Some(model), DeviceId::Serial(_)) = (&status_model, &id) {
+                let info = device.info.read().await;
+                if info.device_type == DeviceType::Grapple(model.clone())
+                    && info.device_id == Some(message.device_id)
+                {
+                    self.emit(DeviceEvent::StatusFrame {
+                        domain: domain.clone(),
+                        id,
+                        message: message.clone(),
+                    });
+                }
+            }
         }
 
         Ok(())
@@ -222,57 +605,400 @@ impl DeviceManager {
         }
 
         // Check age off
-        if let Ok(mut dev_map) = self.devices.try_write() {
-            for (_domain, devices) in dev_map.iter_mut() {
-                devices.retain(|_, device| device.last_seen.elapsed().as_secs() < 4);
+        let age_off = self.age_off.read().await.clone();
+        for (domain, devices) in self.devices.write().iter_mut() {
+            for (id, device) in devices.iter() {
+                let mut liveness = device.liveness.lock().unwrap();
+                let online =
+                    liveness.last_seen.elapsed() < Duration::from_millis(age_off.offline_after_ms);
+                if liveness.online && !online {
+                    warn!("Device {:?} on {} went offline", id, domain);
+                    self.emit(DeviceEvent::PresenceChanged {
+                        domain: domain.clone(),
+                        id: id.clone(),
+                        online,
+                    });
+                }
+                liveness.online = online;
+            }
+
+            if let Some(forget_after_ms) = age_off.forget_after_ms {
+                devices.retain(|id, device| {
+                    let keep = device.liveness.lock().unwrap().last_seen.elapsed()
+                        < Duration::from_millis(forget_after_ms);
+                    if !keep {
+                        self.emit(DeviceEvent::Removed {
+                            domain: domain.clone(),
+                            id: id.clone(),
+                        });
+                    }
+                    keep
+                });
             }
         }
 
         Ok(())
     }
+
+    async fn apply_device_config(
+        &self,
+        snapshot: &DeviceSnapshot,
+        commit: bool,
+    ) -> anyhow::Result<()> {
+        let id = DeviceId::Serial(snapshot.serial);
+        let (domain, device) = self
+            .devices
+            .find(&id)
+            .ok_or(anyhow::anyhow!("No device with serial {}", snapshot.serial))?;
+
+        if let Some(config) = &snapshot.config {
+            device.device.apply_config(config).await?;
+        }
+
+        let info = device.info.read().await.clone();
+        let grapple = GrappleDevice::new(self.sender(&domain), device.info.clone());
+
+        if let Some(name) = &snapshot.name {
+            if info.name.as_ref() != Some(name) {
+                grapple.set_name(name.clone()).await?;
+            }
+        }
+
+        // The ID goes last, since the device-specific settings are addressed by the current ID.
+        if let Some(device_id) = snapshot.device_id {
+            if info.device_id != Some(device_id) {
+                grapple.set_id(device_id).await?;
+            }
+        }
+
+        // Committed once everything's applied, so the new ID is kept along with the rest
+        if commit {
+            grapple.commit_to_eeprom().await?;
+        }
+
+        Ok(())
+    }
 }
 
 #[rpc]
 impl DeviceManager {
+    /// Call an RPC on a device. Requests it makes use the given retry policy instead of the
+    /// provider's, if one is given.
     async fn call(
         &self,
         domain: Domain,
         device_id: DeviceId,
         data: serde_json::Value,
+        retry: Option<RetryPolicy>,
     ) -> anyhow::Result<serde_json::Value> {
-        let result = self
-            .devices
-            .read()
-            .await
-            .get(&domain)
-            .unwrap()
-            .get(&device_id)
-            .ok_or(anyhow::anyhow!("No device with ID {:?}", device_id))?
-            .device
-            .rpc_call(data)
-            .await;
+        Ok(self
+            .call_with_stats(domain, device_id, data, retry)
+            .await?
+            .result)
+    }
 
-        Ok(result?)
+    /// As `call`, but also report how many requests the call made and how many attempts they took.
+    async fn call_with_stats(
+        &self,
+        domain: Domain,
+        device_id: DeviceId,
+        data: serde_json::Value,
+        retry: Option<RetryPolicy>,
+    ) -> anyhow::Result<CallReport> {
+        if let Some(retry) = &retry {
+            retry.validate()?;
+        }
+
+        let device = self
+            .devices
+            .get(&domain, &device_id)
+            .ok_or(anyhow::anyhow!("No device with ID {:?}", device_id))?;
+
+        let (result, stats) = with_request_scope(retry, device.device.rpc_call(data)).await;
+        Ok(CallReport {
+            result: result?,
+            stats,
+        })
     }
 
+    /// All devices, along with the serials of any other devices of the same model sharing their ID,
+    /// and whether they're still online.
     async fn devices(
         &self,
-    ) -> anyhow::Result<HashMap<Domain, Vec<(DeviceId, DeviceInfo, String)>>> {
+    ) -> anyhow::Result<
+        HashMap<Domain, Vec<(DeviceId, DeviceInfo, String, Vec<u32>, DevicePresence)>>,
+    > {
         let mut device_states = HashMap::new();
 
-        let devices = self.devices.read().await;
-        for (domain, devices) in devices.iter() {
+        for (domain, devices) in self.devices.all() {
+            let conflicts = id_conflicts(&devices).await;
             let mut vec = vec![];
             for (id, device) in devices.iter() {
+                let conflicting = match id {
+                    DeviceId::Serial(serial) => conflicts.get(serial).cloned().unwrap_or_default(),
+                    DeviceId::Dfu(_) => vec![],
+                };
                 vec.push((
                     id.clone(),
                     device.info.read().await.clone(),
                     device.device.device_class().to_owned(),
+                    conflicting,
+                    device.presence(),
                 ));
             }
-            device_states.insert(domain.clone(), vec);
+            device_states.insert(domain, vec);
         }
 
         Ok(device_states)
     }
+
+    async fn age_off(&self) -> anyhow::Result<AgeOffConfig> {
+        Ok(self.age_off.read().await.clone())
+    }
+
+    async fn set_age_off(&self, config: AgeOffConfig) -> anyhow::Result<()> {
+        if config
+            .forget_after_ms
+            .map(|forget| forget < config.offline_after_ms)
+            .unwrap_or(false)
+        {
+            anyhow::bail!("Devices can't be forgotten before they're considered offline");
+        }
+        *self.age_off.write().await = config;
+        Ok(())
+    }
+
+    /// The retry policy used for requests to devices on this provider, unless a call overrides it
+    async fn retry_policy(&self) -> anyhow::Result<RetryPolicy> {
+        Ok(self.retry.read().await.clone())
+    }
+
+    async fn set_retry_policy(&self, policy: RetryPolicy) -> anyhow::Result<()> {
+        policy.validate()?;
+        *self.retry.write().await = policy;
+        Ok(())
+    }
+
+    /// Move every device that shares an ID with another device of the same model onto a free ID.
+    /// The device with the lowest serial in each group keeps its ID.
+    async fn resolve_id_conflicts(&self) -> anyhow::Result<Vec<IdReassignment>> {
+        let mut reassignments = vec![];
+
+        for (domain, devices) in self.devices.all() {
+            let conflicts = id_conflicts(&devices).await;
+
+            let mut used_ids: HashMap<u8, HashSet<u8>> = HashMap::new();
+            for (_, device) in devices.iter() {
+                let info = device.info.read().await;
+                if let (DeviceType::Grapple(model), Some(id)) = (&info.device_type, info.device_id)
+                {
+                    used_ids.entry(model.clone() as u8).or_default().insert(id);
+                }
+            }
+
+            let mut serials: Vec<u32> = conflicts
+                .iter()
+                .filter(|(serial, others)| others.iter().any(|o| o < serial))
+                .map(|(serial, _)| *serial)
+                .collect();
+            serials.sort();
+
+            for serial in serials {
+                let Some((_, device)) = devices
+                    .iter()
+                    .find(|(id, _)| *id == DeviceId::Serial(serial))
+                else {
+                    continue;
+                };
+                let info = device.info.read().await.clone();
+                let DeviceType::Grapple(model) = &info.device_type else {
+                    continue;
+                };
+
+                let used = used_ids.entry(model.clone() as u8).or_default();
+                let new_id = (0..DEVICE_ID_BROADCAST)
+                    .find(|id| !used.contains(id))
+                    .ok_or(anyhow::anyhow!(
+                        "No free IDs left for {:?} on {}",
+                        model,
+                        domain
+                    ))?;
+                used.insert(new_id);
+
+                reassignments.push((
+                    IdReassignment {
+                        domain: domain.clone(),
+                        serial,
+                        old_id: info.require_device_id()?,
+                        new_id,
+                    },
+                    GrappleDevice::new(self.sender(&domain), device.info.clone()),
+                ));
+            }
+        }
+
+        // Commit each new ID straight away, so it isn't lost if the device is power cycled
+        for (reassignment, device) in reassignments.iter() {
+            device.set_id(reassignment.new_id).await?;
+            device.commit_to_eeprom().await?;
+        }
+
+        Ok(reassignments.into_iter().map(|(r, _)| r).collect())
+    }
+
+    async fn snapshot_config(&self) -> anyhow::Result<ConfigSnapshot> {
+        let mut snapshot = ConfigSnapshot { devices: vec![] };
+
+        for (_domain, devices) in self.devices.all() {
+            for (id, device) in devices.iter() {
+                // Devices in DFU have no configuration to speak of
+                let DeviceId::Serial(serial) = id else {
+                    continue;
+                };
+
+                let info = device.info.read().await.clone();
+                let config = match device.device.snapshot_config().await {
+                    Ok(config) => config,
+                    Err(e) => {
+                        warn!("Could not snapshot configuration of {}: {}", serial, e);
+                        None
+                    }
+                };
+
+                snapshot.devices.push(DeviceSnapshot {
+                    serial: *serial,
+                    device_type: info.device_type,
+                    device_id: info.device_id,
+                    name: info.name,
+                    config,
+                });
+            }
+        }
+
+        snapshot.devices.sort_by_key(|d| d.serial);
+        Ok(snapshot)
+    }
+
+    async fn export_config(
+        &self,
+        path: String,
+        format: ConfigFormat,
+    ) -> anyhow::Result<ConfigSnapshot> {
+        let snapshot = self.snapshot_config().await?;
+        snapshot.save(&path, &format)?;
+        Ok(snapshot)
+    }
+
+    async fn apply_config(
+        &self,
+        snapshot: ConfigSnapshot,
+        commit: bool,
+    ) -> anyhow::Result<Vec<ConfigApplyResult>> {
+        let mut results = vec![];
+        for device in snapshot.devices.iter() {
+            let error = self
+                .apply_device_config(device, commit)
+                .await
+                .err()
+                .map(|e| e.to_string());
+            results.push(ConfigApplyResult {
+                serial: device.serial,
+                error,
+            });
+        }
+        Ok(results)
+    }
+
+    async fn import_config(
+        &self,
+        path: String,
+        format: ConfigFormat,
+        commit: bool,
+    ) -> anyhow::Result<Vec<ConfigApplyResult>> {
+        let snapshot = ConfigSnapshot::load(&path, &format)?;
+        self.apply_config(snapshot, commit).await
+    }
+
+    async fn start_bulk_field_upgrade(
+        &self,
+        model: GrappleModelId,
+        data: Vec<u8>,
+    ) -> anyhow::Result<BulkUpgradeStatus> {
+        if self.bulk_upgrade.is_running().await {
+            anyhow::bail!("A bulk firmware upgrade is already running");
+        }
+
+        let mut targets = vec![];
+        for (domain, devices) in self.devices.all() {
+            for (id, device) in devices.iter() {
+                // Devices we haven't heard from in a while can't be upgraded, so don't hold the
+                // rest of the upgrade up waiting for them
+                if !device.liveness.lock().unwrap().online {
+                    continue;
+                }
+                let info = device.info.read().await.clone();
+                if info.device_type == DeviceType::Grapple(model.clone()) {
+                    let serial = match id {
+                        DeviceId::Dfu(serial) | DeviceId::Serial(serial) => *serial,
+                    };
+                    targets.push((domain.clone(), serial, info));
+                }
+            }
+        }
+        targets.sort_by_key(|(_, serial, _)| *serial);
+
+        let info = match targets.first() {
+            Some((_, _, info)) => info.clone(),
+            None => anyhow::bail!("No {:?} devices to upgrade", model),
+        };
+
+        let firmware = match model {
+            GrappleModelId::LaserCan => prepare_firmware::<LaserCan>(&info, data)?,
+            GrappleModelId::MitoCANdria => prepare_firmware::<Mitocandria>(&info, data)?,
+            GrappleModelId::FlexiCAN => prepare_firmware::<FlexiCan>(&info, data)?,
+            _ => prepare_firmware::<UnknownGrappleDevice>(&info, data)?,
+        };
+
+        let status = BulkUpgradeStatus {
+            model: model.clone(),
+            running: true,
+            devices: targets
+                .into_iter()
+                .map(|(domain, serial, _)| BulkUpgradeDeviceStatus {
+                    domain,
+                    serial,
+                    state: BulkUpgradeState::Pending,
+                })
+                .collect(),
+        };
+        *self.bulk_upgrade.status.write().await = Some(status.clone());
+
+        let senders = self
+            .send
+            .keys()
+            .map(|domain| (domain.clone(), self.sender(domain)))
+            .collect();
+
+        tokio::task::spawn(self.bulk_upgrade.clone().run(
+            self.devices.clone(),
+            senders,
+            Arc::new(firmware.data),
+            firmware.version,
+            firmware_chunk_size(&model),
+        ));
+
+        Ok(status)
+    }
+
+    async fn bulk_field_upgrade_status(&self) -> anyhow::Result<Option<BulkUpgradeStatus>> {
+        Ok(self.bulk_upgrade.status.read().await.clone())
+    }
+
+    /// Describe a firmware bundle: its version, release notes and the models it can upgrade.
+    async fn inspect_firmware_bundle(&self, data: Vec<u8>) -> anyhow::Result<FirmwareBundleInfo> {
+        match FirmwareBundle::open(&data)? {
+            Some(mut bundle) => bundle.info(),
+            None => anyhow::bail!("Not a firmware bundle"),
+        }
+    }
 }
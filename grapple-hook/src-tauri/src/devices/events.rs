@@ -0,0 +1,111 @@
+use grapple_frc_msgs::grapple::{
+    device_info::GrappleModelId, encapsulation::BridgeMessages, flexican::FlexiCANMessage,
+    lasercan::LaserCanMessage, mitocandria::MitocandriaMessage, GrappleDeviceMessage,
+    TaggedGrappleMessage,
+};
+use log::warn;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use super::{
+    device_manager::{DeviceId, Domain},
+    DeviceInfo,
+};
+
+/// How many events a subscriber can fall behind by before it starts missing them
+pub const EVENT_CAPACITY: usize = 256;
+
+/// Something that happened to a device, pushed to subscribers as it happens so they don't have
+/// to poll the device list or each device's status.
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
+#[serde(tag = "event", content = "data")]
+pub enum DeviceEvent {
+    Added {
+        domain: Domain,
+        id: DeviceId,
+        info: DeviceInfo,
+        device_class: String,
+    },
+    Removed {
+        domain: Domain,
+        id: DeviceId,
+    },
+    PresenceChanged {
+        domain: Domain,
+        id: DeviceId,
+        online: bool,
+    },
+    /// The device has entered or left DFU, so it now lives under the other kind of `DeviceId`
+    DfuTransition {
+        domain: Domain,
+        serial: u32,
+        is_dfu: bool,
+        device_class: String,
+    },
+    IdChanged {
+        domain: Domain,
+        id: DeviceId,
+        old_id: Option<u8>,
+        new_id: Option<u8>,
+    },
+    NameChanged {
+        domain: Domain,
+        id: DeviceId,
+        old_name: Option<String>,
+        new_name: Option<String>,
+    },
+    StatusFrame {
+        domain: Domain,
+        id: DeviceId,
+        message: TaggedGrappleMessage<'static>,
+    },
+}
+
+/// A device event, along with the address of the provider the device is on
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
+pub struct ProviderDeviceEvent {
+    pub provider: String,
+    pub event: DeviceEvent,
+}
+
+/// The model of device that sends the given message as its periodic status, if it is one.
+pub fn status_frame_model(msg: &GrappleDeviceMessage) -> Option<GrappleModelId> {
+    match msg {
+        GrappleDeviceMessage::DistanceSensor(LaserCanMessage::Measurement(_)) => {
+            Some(GrappleModelId::LaserCan)
+        }
+        GrappleDeviceMessage::PowerDistributionModule(MitocandriaMessage::StatusFrame(_)) => {
+            Some(GrappleModelId::MitoCANdria)
+        }
+        GrappleDeviceMessage::IOBreakout(FlexiCANMessage::Bridge(
+            BridgeMessages::BridgeMessage(_),
+        )) => Some(GrappleModelId::FlexiCAN),
+        _ => None,
+    }
+}
+
+/// Re-publish a provider's device events on the provider manager's channel, until the provider
+/// goes away.
+pub fn forward_events(
+    provider: String,
+    mut rx: broadcast::Receiver<DeviceEvent>,
+    tx: broadcast::Sender<ProviderDeviceEvent>,
+) {
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    tx.send(ProviderDeviceEvent {
+                        provider: provider.clone(),
+                        event,
+                    })
+                    .ok(); // ok since there may be nobody listening
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Dropped {} device events from {}", n, provider)
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
@@ -0,0 +1,306 @@
+use std::{
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use chrono::Utc;
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{flexican::FlexiCan, lasercan::LaserCan, mitocandria::Mitocandria, VersionGatedDevice};
+use crate::updates::{import_release_asset, LightReleaseResponse};
+
+/// A firmware binary within a bundle, for a single model of device.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BundleTarget {
+    /// The model this firmware is for. May only be omitted if the bundle has a single target.
+    #[serde(default)]
+    pub model: Option<GrappleModelId>,
+    pub firmware_update_bin: String,
+    /// Hex-encoded SHA-256 of the firmware binary
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Hex-encoded CRC32 of the firmware binary
+    #[serde(default)]
+    pub crc32: Option<String>,
+    /// Overrides the bundle version for this target
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// The `index` file at the root of a firmware bundle. Older bundles declare a single target
+/// at the top level of the index, newer ones list each model's firmware under `targets`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BundleIndex {
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Release notes, inline
+    #[serde(default)]
+    pub release_notes: Option<String>,
+    /// Release notes, as the name of a file within the bundle
+    #[serde(default)]
+    pub release_notes_file: Option<String>,
+    #[serde(default)]
+    pub targets: Vec<BundleTarget>,
+
+    #[serde(default)]
+    firmware_update_bin: Option<String>,
+    #[serde(default)]
+    sha256: Option<String>,
+    #[serde(default)]
+    crc32: Option<String>,
+    #[serde(default)]
+    model: Option<GrappleModelId>,
+}
+
+impl BundleIndex {
+    fn all_targets(&self) -> Vec<BundleTarget> {
+        let mut targets = self.targets.clone();
+        if let Some(bin) = &self.firmware_update_bin {
+            targets.push(BundleTarget {
+                model: self.model.clone(),
+                firmware_update_bin: bin.clone(),
+                sha256: self.sha256.clone(),
+                crc32: self.crc32.clone(),
+                version: None,
+            });
+        }
+        targets
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FirmwareBundleTargetInfo {
+    pub model: Option<GrappleModelId>,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FirmwareBundleInfo {
+    pub version: Option<String>,
+    pub release_notes: Option<String>,
+    pub targets: Vec<FirmwareBundleTargetInfo>,
+}
+
+pub struct FirmwareBundle {
+    archive: zip::ZipArchive<Cursor<Vec<u8>>>,
+    index: BundleIndex,
+    targets: Vec<BundleTarget>,
+}
+
+impl FirmwareBundle {
+    /// Open a firmware bundle. Returns `None` if the data isn't a bundle at all, in which case it
+    /// may be a raw binary.
+    pub fn open(data: &[u8]) -> anyhow::Result<Option<Self>> {
+        let mut archive = match zip::ZipArchive::new(Cursor::new(data.to_vec())) {
+            Ok(archive) => archive,
+            Err(_) => return Ok(None),
+        };
+
+        let mut index_file = archive.by_name("index")?;
+        let mut index_content = String::new();
+        index_file.read_to_string(&mut index_content)?;
+        let index: BundleIndex = serde_json::from_str(&index_content)
+            .map_err(|e| anyhow::anyhow!("Invalid Index: {}", e))?;
+        drop(index_file);
+
+        let targets = index.all_targets();
+        if targets.is_empty() {
+            anyhow::bail!("Invalid Index: the bundle doesn't contain any firmware");
+        }
+        if targets.len() > 1 && targets.iter().any(|t| t.model.is_none()) {
+            anyhow::bail!(
+                "Invalid Index: every target must declare a model in a multi-target bundle"
+            );
+        }
+
+        Ok(Some(Self {
+            archive,
+            index,
+            targets,
+        }))
+    }
+
+    pub fn info(&mut self) -> anyhow::Result<FirmwareBundleInfo> {
+        let release_notes = match self.index.release_notes_file.clone() {
+            Some(file) => {
+                let mut notes = String::new();
+                self.archive.by_name(&file)?.read_to_string(&mut notes)?;
+                Some(notes)
+            }
+            None => self.index.release_notes.clone(),
+        };
+
+        Ok(FirmwareBundleInfo {
+            version: self.index.version.clone(),
+            release_notes,
+            targets: self
+                .targets
+                .iter()
+                .map(|t| FirmwareBundleTargetInfo {
+                    model: t.model.clone(),
+                    version: t.version.clone().or(self.index.version.clone()),
+                })
+                .collect(),
+        })
+    }
+
+    fn target_for(&self, model: &GrappleModelId) -> anyhow::Result<&BundleTarget> {
+        if let Some(target) = self
+            .targets
+            .iter()
+            .find(|t| t.model.as_ref() == Some(model))
+        {
+            return Ok(target);
+        }
+
+        match &self.targets[..] {
+            [target] if target.model.is_none() => Ok(target),
+            [target] => anyhow::bail!(
+                "This firmware is for a {:?}, not a {:?}",
+                target.model.as_ref().unwrap(),
+                model
+            ),
+            _ => anyhow::bail!("This bundle doesn't contain firmware for a {:?}", model),
+        }
+    }
+
+    /// Extract the firmware for the given model, checking it against any checksums declared in
+    /// the index. Also returns the version of the firmware, if known.
+    pub fn firmware_for(
+        &mut self,
+        model: &GrappleModelId,
+    ) -> anyhow::Result<(Vec<u8>, Option<String>)> {
+        let target = self.target_for(model)?.clone();
+
+        let mut firmware_f = self.archive.by_name(&target.firmware_update_bin)?;
+        let mut v = vec![];
+        firmware_f.read_to_end(&mut v)?;
+
+        verify_checksums(&target, &v)?;
+        Ok((v, target.version.or(self.index.version.clone())))
+    }
+}
+
+fn verify_checksums(target: &BundleTarget, data: &[u8]) -> anyhow::Result<()> {
+    if let Some(expected) = &target.sha256 {
+        let actual = format!("{:x}", Sha256::digest(data));
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            anyhow::bail!(
+                "Firmware bundle is corrupt: SHA-256 is {}, expected {}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    if let Some(expected) = &target.crc32 {
+        let expected_crc = u32::from_str_radix(expected.trim().trim_start_matches("0x"), 16)
+            .map_err(|_| anyhow::anyhow!("Invalid CRC32 in bundle index: {}", expected))?;
+        let actual = crc32fast::hash(data);
+        if actual != expected_crc {
+            anyhow::bail!(
+                "Firmware bundle is corrupt: CRC32 is {:08x}, expected {:08x}",
+                actual,
+                expected_crc
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Bundles tag their versions inconsistently, e.g. `v2025.1.0` or `2025.1.0`.
+pub fn normalise_version(version: &str) -> &str {
+    version.trim().trim_start_matches('v')
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ImportedFirmwareBundle {
+    pub file: String,
+    pub targets: Vec<FirmwareBundleTargetInfo>,
+    pub error: Option<String>,
+}
+
+fn release_source(
+    model: &GrappleModelId,
+    version: &Version,
+) -> anyhow::Result<(&'static str, String)> {
+    Ok(match model {
+        GrappleModelId::LaserCan => (LaserCan::release_repo(), LaserCan::release_tag(version)),
+        GrappleModelId::MitoCANdria => (
+            Mitocandria::release_repo(),
+            Mitocandria::release_tag(version),
+        ),
+        GrappleModelId::FlexiCAN => (FlexiCan::release_repo(), FlexiCan::release_tag(version)),
+        m => anyhow::bail!("Firmware releases for {:?} aren't supported", m),
+    })
+}
+
+async fn import_bundle(path: &Path) -> anyhow::Result<Option<Vec<FirmwareBundleTargetInfo>>> {
+    let data = tokio::fs::read(path).await?;
+    let info = match FirmwareBundle::open(&data)? {
+        Some(mut bundle) => bundle.info()?,
+        None => return Ok(None),
+    };
+    let file_name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    for target in info.targets.iter() {
+        let model = target.model.as_ref().ok_or(anyhow::anyhow!(
+            "The bundle doesn't say which model it's for"
+        ))?;
+        let version = target
+            .version
+            .as_ref()
+            .ok_or(anyhow::anyhow!("The bundle doesn't declare a version"))?;
+        let version = Version::parse(normalise_version(version))?;
+        let (repo, tag_name) = release_source(model, &version)?;
+
+        let release = LightReleaseResponse {
+            name: format!("{:?} {} (imported)", model, version),
+            html_url: format!("https://github.com/{}/releases/tag/{}", repo, tag_name),
+            tag_name,
+            published_at: Utc::now().to_rfc3339(),
+            prerelease: false,
+            assets: vec![],
+        };
+        import_release_asset(repo, release, &file_name, &data).await?;
+    }
+
+    Ok(Some(info.targets))
+}
+
+/// Import every firmware bundle in a folder into the firmware cache, as a release of each model
+/// it contains firmware for, so they can be found and flashed without internet access. Files
+/// that aren't bundles are skipped.
+pub async fn import_bundles(dir: &str) -> anyhow::Result<Vec<ImportedFirmwareBundle>> {
+    let mut results = vec![];
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let file = path.display().to_string();
+        match import_bundle(&path).await {
+            Ok(Some(targets)) => results.push(ImportedFirmwareBundle {
+                file,
+                targets,
+                error: None,
+            }),
+            Ok(None) => (),
+            Err(e) => results.push(ImportedFirmwareBundle {
+                file,
+                targets: vec![],
+                error: Some(e.to_string()),
+            }),
+        }
+    }
+    Ok(results)
+}
@@ -1,19 +1,53 @@
+use std::collections::BTreeMap;
+
 use grapple_frc_msgs::{
-    grapple::{device_info::GrappleModelId, GrappleDeviceMessage, TaggedGrappleMessage},
-    DEVICE_ID_BROADCAST,
+    grapple::{
+        device_info::GrappleModelId,
+        encapsulation::{BridgeMessages, EncapsulatedMesssage},
+        errors::GrappleError,
+        flexican::FlexiCANMessage,
+        GrappleDeviceMessage, Request, TaggedGrappleMessage,
+    },
+    request_factory, DEVICE_ID_BROADCAST,
 };
 use grapple_hook_macros::rpc;
 use tokio::sync::RwLock;
 
 use super::{
-    start_field_upgrade, Device, FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest,
-    GrappleDeviceResponse, RootDevice, SendWrapper, SharedInfo, VersionGatedDevice,
+    check_for_new_firmware_release_rpc_target, start_field_upgrade, Device,
+    FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest, GrappleDeviceResponse,
+    HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo, VersionGatedDevice,
+};
+use crate::{
+    rpc::RpcBase,
+    updates::{most_recent_update_available, LightReleaseResponse},
 };
-use crate::{rpc::RpcBase, updates::LightReleaseResponse};
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FlexiCanChannelStatus {
+    pub channel: u8,
+    pub name: Option<String>,
+    pub bridging: bool,
+    /// Number of frames bridged from this channel since the device was found
+    pub frames_bridged: u64,
+    pub last_frame: Option<EncapsulatedMesssage<'static>>,
+}
+
+impl FlexiCanChannelStatus {
+    fn new(channel: u8) -> Self {
+        Self {
+            channel,
+            name: None,
+            bridging: false,
+            frames_bridged: 0,
+            last_frame: None,
+        }
+    }
+}
 
 #[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct FlexiCanStatus {
-    // last_update: Option<powerful_panda::StatusFrame>
+    pub channels: Vec<FlexiCanChannelStatus>,
 }
 
 pub struct FlexiCan {
@@ -22,7 +56,7 @@ pub struct FlexiCan {
 
     grapple_device: GrappleDevice,
 
-    status: RwLock<FlexiCanStatus>,
+    channels: RwLock<BTreeMap<u8, FlexiCanChannelStatus>>,
 }
 
 impl FlexiCan {
@@ -33,22 +67,58 @@ impl FlexiCan {
 
             grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
 
-            status: RwLock::new(FlexiCanStatus {}),
+            channels: RwLock::new(BTreeMap::new()),
         }
     }
+
+    async fn update_channel<F: FnOnce(&mut FlexiCanChannelStatus)>(&self, channel: u8, f: F) {
+        let mut channels = self.channels.write().await;
+        f(channels
+            .entry(channel)
+            .or_insert_with(|| FlexiCanChannelStatus::new(channel)));
+    }
+}
+
+impl HasFirmwareUpdateURLDevice for FlexiCan {
+    fn firmware_url() -> Option<String> {
+        Some("https://github.com/GrappleRobotics/Binaries/releases".to_owned())
+    }
 }
 
-// #[async_trait::async_trait]
-// impl VersionGatedDevice for FlexiCan {
-//   fn validate_version(version: Option<String>) -> anyhow::Result<()> {
-//     // Self::require_version(version, ">= 2024.2.0, < 2024.3.0")
-//     Ok(())
-//   }
+#[async_trait::async_trait]
+impl VersionGatedDevice for FlexiCan {
+    fn validate_version(version: Option<String>) -> anyhow::Result<()> {
+        Self::require_version(version, ">= 2025.0.0, < 2026.1.0")
+    }
+
+    async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse> {
+        let current = semver::Version::parse(&current_version).ok()?;
+
+        most_recent_update_available(Self::release_repo(), |release| {
+            match release
+                .tag_name
+                .strip_prefix("flexican-v")
+                .and_then(|v| semver::Version::parse(v).ok())
+            {
+                Some(vers) => {
+                    vers > current && Self::validate_version(Some(vers.to_string())).is_ok()
+                }
+                None => false,
+            }
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    fn release_repo() -> &'static str {
+        "GrappleRobotics/Binaries"
+    }
 
-//   async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>{
-//     None
-//   }
-// }
+    fn release_tag(version: &semver::Version) -> String {
+        format!("flexican-v{}", version)
+    }
+}
 
 #[async_trait::async_trait]
 impl RootDevice for FlexiCan {
@@ -64,9 +134,15 @@ impl Device for FlexiCan {
             || Some(msg.device_id) == self.info.read().await.device_id
         {
             match msg.clone().msg {
-                GrappleDeviceMessage::Broadcast(bcast) => match bcast {
-                    _ => (),
-                },
+                GrappleDeviceMessage::IOBreakout(FlexiCANMessage::Bridge(
+                    BridgeMessages::BridgeMessage(frame),
+                )) => {
+                    self.update_channel(frame.channel, |channel| {
+                        channel.frames_bridged += 1;
+                        channel.last_frame = Some(frame);
+                    })
+                    .await;
+                }
                 _ => (),
             }
         }
@@ -77,6 +153,14 @@ impl Device for FlexiCan {
 }
 
 impl FirmwareValidatingDevice for FlexiCan {
+    fn model_id(_info: &super::DeviceInfo) -> anyhow::Result<GrappleModelId> {
+        Ok(GrappleModelId::FlexiCAN)
+    }
+
+    fn validate_firmware_version(version: &str) -> anyhow::Result<()> {
+        Self::validate_version(Some(version.to_owned()))
+    }
+
     fn validate_firmware(_info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
         if &buf[0x200..0x204] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8]
             && buf[0x20c] == (GrappleModelId::FlexiCAN as u8)
@@ -100,6 +184,71 @@ impl FlexiCan {
     }
 
     async fn status(&self) -> anyhow::Result<FlexiCanStatus> {
-        Ok(self.status.read().await.clone())
+        Ok(FlexiCanStatus {
+            channels: self.channels.read().await.values().cloned().collect(),
+        })
+    }
+
+    async fn get_channel_name(&self, channel: u8) -> anyhow::Result<String> {
+        let id = self.info.read().await.require_device_id()?;
+        let (encode, decode) = request_factory!(
+            data,
+            GrappleDeviceMessage::IOBreakout(FlexiCANMessage::Bridge(
+                BridgeMessages::GetChannelName(data)
+            ))
+        );
+
+        let msg = self
+            .sender
+            .request(TaggedGrappleMessage::new(id, encode(channel)))
+            .await?;
+        let name = decode(msg.msg)??.0.to_string();
+
+        self.update_channel(channel, |c| c.name = Some(name.clone()))
+            .await;
+        Ok(name)
+    }
+
+    /// Start forwarding frames from one of the FlexiCAN's buses
+    async fn start_bridge(&self, channel: u8) -> anyhow::Result<()> {
+        let id = self.info.read().await.require_device_id()?;
+        let (encode, decode) = request_factory!(
+            data,
+            GrappleDeviceMessage::IOBreakout(FlexiCANMessage::Bridge(BridgeMessages::StartBridge(
+                data
+            )))
+        );
+
+        let msg = self
+            .sender
+            .request(TaggedGrappleMessage::new(id, encode(channel)))
+            .await?;
+        decode(msg.msg)??;
+
+        self.update_channel(channel, |c| c.bridging = true).await;
+        Ok(())
+    }
+
+    async fn stop_bridge(&self, channel: u8) -> anyhow::Result<()> {
+        let id = self.info.read().await.require_device_id()?;
+        let (encode, decode) = request_factory!(
+            data,
+            GrappleDeviceMessage::IOBreakout(FlexiCANMessage::Bridge(BridgeMessages::StopBridge(
+                data
+            )))
+        );
+
+        let msg = self
+            .sender
+            .request(TaggedGrappleMessage::new(id, encode(channel)))
+            .await?;
+        decode(msg.msg)??;
+
+        self.update_channel(channel, |c| c.bridging = false).await;
+        Ok(())
+    }
+
+    async fn check_for_new_firmware(&self) -> anyhow::Result<Option<LightReleaseResponse>> {
+        check_for_new_firmware_release_rpc_target::<Self>(&self.info).await
     }
 }
@@ -17,7 +17,7 @@ use grapple_frc_msgs::{
 };
 use log::{info, warn};
 use serde_json::json;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio_serial::{SerialPort, SerialStream, UsbPortInfo};
 use tokio_util::codec::Framed;
 
@@ -25,6 +25,7 @@ use crate::codecs::usb_codec::GrappleUsbCodec;
 
 use super::{
     device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse},
+    events::DeviceEvent,
     provider::{DeviceProvider, ProviderInfo},
 };
 
@@ -189,6 +190,10 @@ impl DeviceProvider for GenericUSB {
         Ok(json!({}))
     }
 
+    fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.inner.device_manager.subscribe()
+    }
+
     async fn device_manager_call(
         &self,
         req: DeviceManagerRequest,
@@ -0,0 +1,109 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct HistoryItem<T> {
+    /// Milliseconds since the history was created
+    pub timestamp: u32,
+    pub value: T,
+}
+
+/// A bounded, timestamped history of values received from a device. Items are dropped once
+/// there are more than `max_len`, or once they're older than `max_age` if set.
+pub struct History<T> {
+    rel_epoch: Instant,
+    max_len: usize,
+    max_age: Option<Duration>,
+    items: VecDeque<HistoryItem<T>>,
+}
+
+impl<T: Clone> History<T> {
+    pub fn new(max_len: usize, max_age: Option<Duration>) -> Self {
+        Self {
+            rel_epoch: Instant::now(),
+            max_len,
+            max_age,
+            items: VecDeque::with_capacity(max_len),
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        let timestamp = self.rel_epoch.elapsed().as_millis() as u32;
+        self.items.push_back(HistoryItem { timestamp, value });
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.items.len() > self.max_len {
+            self.items.pop_front();
+        }
+
+        if let Some(max_age) = self.max_age {
+            let now = self.rel_epoch.elapsed().as_millis() as u32;
+            let max_age = max_age.as_millis() as u32;
+            while self
+                .items
+                .front()
+                .map(|x| now.saturating_sub(x.timestamp) > max_age)
+                .unwrap_or(false)
+            {
+                self.items.pop_front();
+            }
+        }
+    }
+
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+        self.trim();
+    }
+
+    pub fn set_max_age(&mut self, max_age: Option<Duration>) {
+        self.max_age = max_age;
+        self.trim();
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn items(&mut self) -> Vec<HistoryItem<T>> {
+        // Items only age off when new ones arrive, so make sure stale ones don't linger if the device has gone quiet.
+        self.trim();
+        self.items.iter().cloned().collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SeriesStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Population standard deviation
+    pub stddev: f64,
+}
+
+impl SeriesStats {
+    pub fn calculate(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let values: Vec<f64> = values.into_iter().collect();
+        if values.is_empty() {
+            return None;
+        }
+
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count as f64;
+
+        Some(Self {
+            count,
+            min: values.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            stddev: variance.sqrt(),
+        })
+    }
+}
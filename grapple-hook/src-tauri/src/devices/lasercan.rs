@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use grapple_frc_msgs::{
     grapple::{
         device_info::GrappleModelId,
@@ -14,20 +16,43 @@ use grapple_hook_macros::rpc;
 use tokio::sync::RwLock;
 
 use super::{
-    check_for_new_firmware_release_rpc_target, start_field_upgrade, Device,
-    FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest, GrappleDeviceResponse,
-    HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo, VersionGatedDevice,
+    check_for_new_firmware_release_rpc_target,
+    history::{History, HistoryItem, SeriesStats},
+    snapshot::DeviceConfig,
+    start_field_upgrade, Device, FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest,
+    GrappleDeviceResponse, HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo,
+    VersionGatedDevice,
 };
 use crate::{
     rpc::RpcBase,
     updates::{most_recent_update_available, LightReleaseResponse},
 };
 
+/// The configuration currently stored on the LaserCAN, as reported in its measurement frames.
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LaserCanConfig {
+    pub mode: LaserCanRangingMode,
+    pub roi: LaserCanRoi,
+    pub budget: LaserCanTimingBudget,
+}
+
 #[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct LaserCanStatus {
     last_update: Option<LaserCanMeasurement>,
+    config: Option<LaserCanConfig>,
 }
 
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LaserCanHistory {
+    /// Distance statistics over valid (status 0) measurements only
+    distance_mm: Option<SeriesStats>,
+    /// Number of measurements with each status code
+    status_counts: BTreeMap<u8, usize>,
+    series: Vec<HistoryItem<LaserCanMeasurement>>,
+}
+
+const DEFAULT_HISTORY_LENGTH: usize = 500;
+
 pub struct LaserCan {
     sender: SendWrapper,
     info: SharedInfo,
@@ -35,6 +60,7 @@ pub struct LaserCan {
     grapple_device: GrappleDevice,
 
     status: RwLock<LaserCanStatus>,
+    history: RwLock<History<LaserCanMeasurement>>,
 }
 
 impl LaserCan {
@@ -45,7 +71,11 @@ impl LaserCan {
 
             grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
 
-            status: RwLock::new(LaserCanStatus { last_update: None }),
+            status: RwLock::new(LaserCanStatus {
+                last_update: None,
+                config: None,
+            }),
+            history: RwLock::new(History::new(DEFAULT_HISTORY_LENGTH, None)),
         }
     }
 }
@@ -65,7 +95,7 @@ impl VersionGatedDevice for LaserCan {
     async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse> {
         let current = semver::Version::parse(&current_version).ok()?;
 
-        most_recent_update_available("https://github.com/GrappleRobotics/LaserCAN", |release| {
+        most_recent_update_available(Self::release_repo(), |release| {
             let vers = semver::Version::parse(&release.tag_name[1..]).ok();
             if let Some(vers) = vers {
                 vers > current && Self::validate_version(Some(vers.to_string())).is_ok()
@@ -77,6 +107,14 @@ impl VersionGatedDevice for LaserCan {
         .ok()
         .flatten()
     }
+
+    fn release_repo() -> &'static str {
+        "GrappleRobotics/LaserCAN"
+    }
+
+    fn release_tag(version: &semver::Version) -> String {
+        format!("v{}", version)
+    }
 }
 
 #[async_trait::async_trait]
@@ -84,6 +122,27 @@ impl RootDevice for LaserCan {
     fn device_class(&self) -> &'static str {
         "LaserCAN"
     }
+
+    async fn snapshot_config(&self) -> anyhow::Result<Option<DeviceConfig>> {
+        let config = self.get_config().await?;
+        Ok(Some(DeviceConfig::LaserCan {
+            mode: config.mode,
+            roi: config.roi,
+            budget: config.budget,
+        }))
+    }
+
+    async fn apply_config(&self, config: &DeviceConfig) -> anyhow::Result<()> {
+        match config {
+            DeviceConfig::LaserCan { mode, roi, budget } => {
+                self.set_range(mode.clone()).await?;
+                self.set_roi(roi.clone()).await?;
+                self.set_timing_budget(budget.clone()).await?;
+                Ok(())
+            }
+            _ => anyhow::bail!("Not a LaserCAN configuration"),
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -98,7 +157,14 @@ impl Device for LaserCan {
                 },
                 GrappleDeviceMessage::DistanceSensor(sensor) => match sensor {
                     LaserCanMessage::Measurement(measurement) => {
-                        self.status.write().await.last_update = Some(measurement);
+                        let mut status = self.status.write().await;
+                        status.config = Some(LaserCanConfig {
+                            mode: measurement.mode.clone(),
+                            roi: measurement.roi.clone(),
+                            budget: measurement.budget.clone(),
+                        });
+                        self.history.write().await.push(measurement.clone());
+                        status.last_update = Some(measurement);
                     }
                     _ => (),
                 },
@@ -112,6 +178,14 @@ impl Device for LaserCan {
 }
 
 impl FirmwareValidatingDevice for LaserCan {
+    fn model_id(_info: &super::DeviceInfo) -> anyhow::Result<GrappleModelId> {
+        Ok(GrappleModelId::LaserCan)
+    }
+
+    fn validate_firmware_version(version: &str) -> anyhow::Result<()> {
+        Self::validate_version(Some(version.to_owned()))
+    }
+
     fn validate_firmware(_info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
         if &buf[0x150..0x154] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8]
             && buf[0x15c] == (GrappleModelId::LaserCan as u8)
@@ -139,9 +213,13 @@ impl LaserCan {
 
         let msg = self
             .sender
-            .request(TaggedGrappleMessage::new(id, encode(mode)), 300, 5)
+            .request(TaggedGrappleMessage::new(id, encode(mode.clone())))
             .await?;
         decode(msg.msg)??;
+
+        if let Some(config) = self.status.write().await.config.as_mut() {
+            config.mode = mode;
+        }
         Ok(())
     }
 
@@ -154,9 +232,13 @@ impl LaserCan {
 
         let msg = self
             .sender
-            .request(TaggedGrappleMessage::new(id, encode(roi)), 300, 5)
+            .request(TaggedGrappleMessage::new(id, encode(roi.clone())))
             .await?;
         decode(msg.msg)??;
+
+        if let Some(config) = self.status.write().await.config.as_mut() {
+            config.roi = roi;
+        }
         Ok(())
     }
 
@@ -169,9 +251,13 @@ impl LaserCan {
 
         let msg = self
             .sender
-            .request(TaggedGrappleMessage::new(id, encode(budget)), 300, 5)
+            .request(TaggedGrappleMessage::new(id, encode(budget.clone())))
             .await?;
         decode(msg.msg)??;
+
+        if let Some(config) = self.status.write().await.config.as_mut() {
+            config.budget = budget;
+        }
         Ok(())
     }
 
@@ -183,6 +269,50 @@ impl LaserCan {
         Ok(self.status.read().await.clone())
     }
 
+    async fn history(&self) -> anyhow::Result<LaserCanHistory> {
+        let series = self.history.write().await.items();
+
+        let mut status_counts = BTreeMap::new();
+        for item in series.iter() {
+            *status_counts.entry(item.value.status).or_insert(0) += 1;
+        }
+
+        Ok(LaserCanHistory {
+            distance_mm: SeriesStats::calculate(
+                series
+                    .iter()
+                    .filter(|x| x.value.status == 0)
+                    .map(|x| x.value.distance_mm as f64),
+            ),
+            status_counts,
+            series,
+        })
+    }
+
+    async fn set_history_length(&self, length: usize) -> anyhow::Result<()> {
+        if length == 0 {
+            anyhow::bail!("History length must be at least 1");
+        }
+        self.history.write().await.set_max_len(length);
+        Ok(())
+    }
+
+    async fn clear_history(&self) -> anyhow::Result<()> {
+        self.history.write().await.clear();
+        Ok(())
+    }
+
+    async fn get_config(&self) -> anyhow::Result<LaserCanConfig> {
+        self.status
+            .read()
+            .await
+            .config
+            .clone()
+            .ok_or(anyhow::anyhow!(
+                "No configuration received from LaserCAN yet"
+            ))
+    }
+
     async fn check_for_new_firmware(&self) -> anyhow::Result<Option<LightReleaseResponse>> {
         check_for_new_firmware_release_rpc_target::<Self>(&self.info).await
     }
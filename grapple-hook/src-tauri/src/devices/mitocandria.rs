@@ -1,4 +1,4 @@
-use std::thread::current;
+use std::{fmt::Write, path::Path, thread::current, time::Duration};
 
 use grapple_frc_msgs::{
     grapple::{
@@ -16,9 +16,12 @@ use grapple_hook_macros::rpc;
 use tokio::sync::RwLock;
 
 use super::{
-    check_for_new_firmware_release_rpc_target, start_field_upgrade, Device,
-    FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest, GrappleDeviceResponse,
-    HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo, VersionGatedDevice,
+    check_for_new_firmware_release_rpc_target,
+    history::{History, SeriesStats},
+    snapshot::{DeviceConfig, MitocandriaChannelConfig},
+    start_field_upgrade, Device, FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest,
+    GrappleDeviceResponse, HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo,
+    VersionGatedDevice,
 };
 use crate::{
     rpc::RpcBase,
@@ -30,6 +33,38 @@ pub struct MitocandriaStatus {
     last_update: Option<mitocandria::MitocandriaStatusFrame>,
 }
 
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MitocandriaChannelSample {
+    /// Milliseconds since the history was created
+    timestamp: u32,
+    /// Current in mA
+    current: u16,
+    /// Voltage in mV, for adjustable channels
+    voltage: Option<u16>,
+    enabled: Option<bool>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MitocandriaChannelHistory {
+    channel: u8,
+    current: Option<SeriesStats>,
+    voltage: Option<SeriesStats>,
+    series: Vec<MitocandriaChannelSample>,
+}
+
+const DEFAULT_HISTORY_WINDOW: Duration = Duration::from_secs(30 * 60);
+// Upper bound on memory use in case the status frame rate is higher than expected
+const MAX_HISTORY_LENGTH: usize = 100_000;
+// As far back as MAX_HISTORY_LENGTH reaches at the usual 10Hz status rate
+const MAX_HISTORY_WINDOW: Duration = Duration::from_secs(MAX_HISTORY_LENGTH as u64 / 10);
+
+async fn is_dir(path: &Path) -> bool {
+    tokio::fs::metadata(path)
+        .await
+        .map(|m| m.is_dir())
+        .unwrap_or(false)
+}
+
 pub struct Mitocandria {
     sender: SendWrapper,
     info: SharedInfo,
@@ -37,6 +72,7 @@ pub struct Mitocandria {
     grapple_device: GrappleDevice,
 
     status: RwLock<MitocandriaStatus>,
+    history: RwLock<History<mitocandria::MitocandriaStatusFrame>>,
 }
 
 impl Mitocandria {
@@ -48,8 +84,64 @@ impl Mitocandria {
             grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
 
             status: RwLock::new(MitocandriaStatus { last_update: None }),
+            history: RwLock::new(History::new(
+                MAX_HISTORY_LENGTH,
+                Some(DEFAULT_HISTORY_WINDOW),
+            )),
         }
     }
+
+    async fn channel_histories(&self) -> Vec<MitocandriaChannelHistory> {
+        let frames = self.history.write().await.items();
+
+        let mut histories: Vec<MitocandriaChannelHistory> = (0..5)
+            .map(|i| MitocandriaChannelHistory {
+                channel: i,
+                current: None,
+                voltage: None,
+                series: Vec::with_capacity(frames.len()),
+            })
+            .collect();
+
+        for frame in frames.iter() {
+            for (i, channel) in frame.value.channels.iter().enumerate() {
+                let (current, voltage, enabled) = match channel {
+                    mitocandria::MitocandriaChannelStatus::NonSwitchable { current } => {
+                        (*current, None, None)
+                    }
+                    mitocandria::MitocandriaChannelStatus::Switchable { enabled, current } => {
+                        (*current, None, Some(*enabled))
+                    }
+                    mitocandria::MitocandriaChannelStatus::Adjustable {
+                        enabled,
+                        voltage,
+                        current,
+                        ..
+                    } => (*current, Some(*voltage), Some(*enabled)),
+                };
+
+                histories[i].series.push(MitocandriaChannelSample {
+                    timestamp: frame.timestamp,
+                    current,
+                    voltage,
+                    enabled,
+                });
+            }
+        }
+
+        for history in histories.iter_mut() {
+            history.current =
+                SeriesStats::calculate(history.series.iter().map(|x| x.current as f64));
+            history.voltage = SeriesStats::calculate(
+                history
+                    .series
+                    .iter()
+                    .filter_map(|x| x.voltage.map(|v| v as f64)),
+            );
+        }
+
+        histories
+    }
 }
 
 impl HasFirmwareUpdateURLDevice for Mitocandria {
@@ -68,9 +160,8 @@ impl VersionGatedDevice for Mitocandria {
         let regex = regex::Regex::new("^([a-zA-Z0-9_]+)-v?(.+)$").unwrap();
         let current = semver::Version::parse(&current_version).ok()?;
 
-        most_recent_update_available(
-            "https://api.github.com/repos/GrappleRobotics/Binaries/releases",
-            |release| match release.tag_name.to_string() {
+        most_recent_update_available(Self::release_repo(), |release| {
+            match release.tag_name.to_string() {
                 x if x.starts_with("mitocandria") => {
                     if let Some(captures) = regex.captures(x.as_str()) {
                         let vers = semver::Version::parse(
@@ -88,12 +179,20 @@ impl VersionGatedDevice for Mitocandria {
                     }
                 }
                 _ => false,
-            },
-        )
+            }
+        })
         .await
         .ok()
         .flatten()
     }
+
+    fn release_repo() -> &'static str {
+        "GrappleRobotics/Binaries"
+    }
+
+    fn release_tag(version: &semver::Version) -> String {
+        format!("mitocandria-v{}", version)
+    }
 }
 
 #[async_trait::async_trait]
@@ -101,6 +200,67 @@ impl RootDevice for Mitocandria {
     fn device_class(&self) -> &'static str {
         "MitoCANdria"
     }
+
+    async fn snapshot_config(&self) -> anyhow::Result<Option<DeviceConfig>> {
+        let status = self.status.read().await;
+        let frame = status
+            .last_update
+            .as_ref()
+            .ok_or(anyhow::anyhow!("No status received from MitoCANdria yet"))?;
+
+        let channels = frame
+            .channels
+            .iter()
+            .enumerate()
+            .filter_map(|(i, channel)| match channel {
+                mitocandria::MitocandriaChannelStatus::NonSwitchable { .. } => None,
+                mitocandria::MitocandriaChannelStatus::Switchable { enabled, .. } => {
+                    Some(MitocandriaChannelConfig {
+                        channel: i as u8,
+                        enabled: Some(*enabled),
+                        voltage: None,
+                    })
+                }
+                mitocandria::MitocandriaChannelStatus::Adjustable {
+                    enabled,
+                    voltage_setpoint,
+                    ..
+                } => Some(MitocandriaChannelConfig {
+                    channel: i as u8,
+                    enabled: Some(*enabled),
+                    voltage: Some(*voltage_setpoint),
+                }),
+            })
+            .collect();
+
+        Ok(Some(DeviceConfig::Mitocandria { channels }))
+    }
+
+    async fn apply_config(&self, config: &DeviceConfig) -> anyhow::Result<()> {
+        match config {
+            DeviceConfig::Mitocandria { channels } => {
+                for channel in channels {
+                    // Set the voltage before enabling, so the output never sees the old setpoint
+                    if let Some(voltage) = channel.voltage {
+                        self.set_adjustable_channel(MitocandriaAdjustableChannelRequest {
+                            channel: channel.channel,
+                            voltage,
+                        })
+                        .await?;
+                    }
+                    if let Some(enabled) = channel.enabled {
+                        self.set_switchable_channel(MitocandriaSwitchableChannelRequest {
+                            channel: channel.channel,
+                            enabled,
+                        })
+                        .await?;
+                    }
+                }
+                Ok(())
+            }
+            _ => anyhow::bail!("Not a MitoCANdria configuration"),
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -115,6 +275,7 @@ impl Device for Mitocandria {
                 },
                 GrappleDeviceMessage::PowerDistributionModule(pdm) => match pdm {
                     mitocandria::MitocandriaMessage::StatusFrame(status) => {
+                        self.history.write().await.push(status.clone());
                         self.status.write().await.last_update = Some(status);
                     }
                     _ => (),
@@ -129,6 +290,14 @@ impl Device for Mitocandria {
 }
 
 impl FirmwareValidatingDevice for Mitocandria {
+    fn model_id(_info: &super::DeviceInfo) -> anyhow::Result<GrappleModelId> {
+        Ok(GrappleModelId::MitoCANdria)
+    }
+
+    fn validate_firmware_version(version: &str) -> anyhow::Result<()> {
+        Self::validate_version(Some(version.to_owned()))
+    }
+
     fn validate_firmware(_info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
         if &buf[0x200..0x204] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8]
             && buf[0x20c] == (GrappleModelId::MitoCANdria as u8)
@@ -163,7 +332,7 @@ impl Mitocandria {
 
         let msg = self
             .sender
-            .request(TaggedGrappleMessage::new(id, encode(channel)), 300, 5)
+            .request(TaggedGrappleMessage::new(id, encode(channel)))
             .await?;
         decode(msg.msg)??;
         Ok(())
@@ -185,7 +354,7 @@ impl Mitocandria {
 
         let msg = self
             .sender
-            .request(TaggedGrappleMessage::new(id, encode(channel)), 300, 5)
+            .request(TaggedGrappleMessage::new(id, encode(channel)))
             .await?;
         decode(msg.msg)??;
         Ok(())
@@ -204,7 +373,7 @@ impl Mitocandria {
 
         let msg = self
             .sender
-            .request(TaggedGrappleMessage::new(id, encode(())), 300, 5)
+            .request(TaggedGrappleMessage::new(id, encode(())))
             .await?;
         decode(msg.msg)??;
         Ok(())
@@ -218,6 +387,70 @@ impl Mitocandria {
         Ok(self.status.read().await.clone())
     }
 
+    async fn history(&self) -> anyhow::Result<Vec<MitocandriaChannelHistory>> {
+        Ok(self.channel_histories().await)
+    }
+
+    async fn set_history_window(&self, window_s: u64) -> anyhow::Result<()> {
+        if window_s == 0 || window_s > MAX_HISTORY_WINDOW.as_secs() {
+            anyhow::bail!(
+                "History window must be between 1 and {} seconds",
+                MAX_HISTORY_WINDOW.as_secs()
+            );
+        }
+        self.history
+            .write()
+            .await
+            .set_max_age(Some(Duration::from_secs(window_s)));
+        Ok(())
+    }
+
+    async fn clear_history(&self) -> anyhow::Result<()> {
+        self.history.write().await.clear();
+        Ok(())
+    }
+
+    async fn export_history_csv(&self, path: String) -> anyhow::Result<()> {
+        let path = Path::new(&path);
+        if path.as_os_str().is_empty() || is_dir(path).await {
+            anyhow::bail!("Not a file path: {}", path.display());
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            if !is_dir(parent).await {
+                anyhow::bail!("No such directory: {}", parent.display());
+            }
+        }
+
+        let histories = self.channel_histories().await;
+        let mut csv = String::new();
+
+        write!(csv, "timestamp_ms")?;
+        for history in histories.iter() {
+            let ch = history.channel;
+            write!(csv, ",ch{ch}_current_ma,ch{ch}_voltage_mv,ch{ch}_enabled")?;
+        }
+        writeln!(csv)?;
+
+        let nsamples = histories.first().map(|x| x.series.len()).unwrap_or(0);
+        for i in 0..nsamples {
+            write!(csv, "{}", histories[0].series[i].timestamp)?;
+            for history in histories.iter() {
+                let sample = &history.series[i];
+                write!(
+                    csv,
+                    ",{},{},{}",
+                    sample.current,
+                    sample.voltage.map(|x| x.to_string()).unwrap_or_default(),
+                    sample.enabled.map(|x| x.to_string()).unwrap_or_default()
+                )?;
+            }
+            writeln!(csv)?;
+        }
+
+        tokio::fs::write(path, csv).await?;
+        Ok(())
+    }
+
     async fn check_for_new_firmware(&self) -> anyhow::Result<Option<LightReleaseResponse>> {
         check_for_new_firmware_release_rpc_target::<Self>(&self.info).await
     }
@@ -1,21 +1,25 @@
+pub mod bulk_upgrade;
 pub mod device_manager;
+pub mod events;
+pub mod firmware_bundle;
 pub mod flexican;
 pub mod generic_usb;
+pub mod history;
 pub mod lasercan;
 pub mod mitocandria;
 pub mod provider;
 pub mod provider_manager;
+pub mod replay;
+pub mod retry;
 pub mod roborio;
+pub mod simulator;
+pub mod snapshot;
+#[cfg(target_os = "linux")]
+pub mod socketcan;
+pub mod upgrade_checkpoint;
 // pub mod powerful_panda;
 
-use std::{
-    borrow::Cow,
-    collections::HashMap,
-    io::{Cursor, Read},
-    marker::PhantomData,
-    sync::Arc,
-    time::Duration,
-};
+use std::{borrow::Cow, marker::PhantomData, sync::Arc, time::Duration};
 
 use bounded_static::IntoBoundedStatic;
 use grapple_frc_msgs::{
@@ -29,18 +33,30 @@ use grapple_frc_msgs::{
     request_factory, MessageId, Validate, DEVICE_ID_BROADCAST,
 };
 use grapple_hook_macros::rpc;
-use log::info;
+use log::{debug, info, warn};
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc, oneshot, Notify, RwLock};
-use uuid::Uuid;
+use tokio::sync::{mpsc, Notify, RwLock};
 
-use crate::{rpc::RpcBase, updates::LightReleaseResponse};
+use crate::{
+    rpc::RpcBase,
+    updates::{choose_firmware_asset, download_asset, LightReleaseResponse},
+};
 
-use self::device_manager::RepliesWaiting;
+use self::{
+    device_manager::RepliesWaiting,
+    firmware_bundle::FirmwareBundle,
+    retry::{record_request, scoped_policy, RetryPolicy},
+    snapshot::DeviceConfig,
+    upgrade_checkpoint::{ActiveUpgrade, UpgradeCheckpoint},
+};
 
 #[derive(Clone)]
-pub struct SendWrapper(mpsc::Sender<TaggedGrappleMessage<'static>>, RepliesWaiting);
+pub struct SendWrapper(
+    mpsc::Sender<TaggedGrappleMessage<'static>>,
+    RepliesWaiting,
+    Arc<RwLock<RetryPolicy>>,
+);
 
 impl SendWrapper {
     async fn send(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
@@ -53,39 +69,31 @@ impl SendWrapper {
         &self,
         msg: TaggedGrappleMessage<'static>,
         reply_id: GrappleMessageId,
-        timeout_ms: usize,
+        timeout: Duration,
     ) -> anyhow::Result<TaggedGrappleMessage> {
         let complement_id_u32: u32 = Into::<MessageId>::into(reply_id).into();
 
-        let uuid = Uuid::new_v4();
-
-        let (tx, rx) = oneshot::channel();
-        {
-            let mut hm = self.1.write().await;
-            if !hm.contains_key(&complement_id_u32) {
-                hm.insert(complement_id_u32, HashMap::new());
-            }
-            hm.get_mut(&complement_id_u32).unwrap().insert(uuid, tx);
-            drop(hm);
+        let rx = self.1.wait_for(complement_id_u32).await;
+        if let Err(e) = self.send(msg).await {
+            self.1.cancel(complement_id_u32).await;
+            return Err(e);
         }
-        self.send(msg).await?;
 
-        match tokio::time::timeout(Duration::from_millis(timeout_ms as u64), rx).await {
+        match tokio::time::timeout(timeout, rx).await {
             Ok(result) => result.map_err(|e| anyhow::anyhow!(e)),
-            Err(_) => {
+            Err(elapsed) => {
                 // Timed out - remove it from the replies waiting
-                let mut hm = self.1.write().await;
-                hm.get_mut(&complement_id_u32).map(|x| x.remove(&uuid));
-                anyhow::bail!("Timed out waiting for response")
+                self.1.cancel(complement_id_u32).await;
+                Err(anyhow::Error::new(elapsed))
             }
         }
     }
 
+    /// Send a request and wait for its reply, retrying according to the retry policy of the RPC
+    /// call we're part of, or the provider's if it didn't ask for one.
     async fn request(
         &self,
         mut msg: TaggedGrappleMessage<'static>,
-        timeout_ms: usize,
-        retry: usize,
     ) -> anyhow::Result<TaggedGrappleMessage> {
         let mut id = GrappleMessageId::new(msg.device_id);
         msg.msg.update(&mut id);
@@ -93,18 +101,49 @@ impl SendWrapper {
         let mut complement_id = id.clone();
         complement_id.ack_flag = true;
 
-        match self
-            .request_inner(msg.clone(), complement_id, timeout_ms)
-            .await
-        {
-            Ok(x) => Ok(x),
-            Err(_) if retry >= 1 => Box::pin(self.request(msg, timeout_ms, retry - 1)).await,
-            Err(e) => Err(e),
+        // Held across retries, so a late reply to an earlier attempt can only ever go to us
+        let _turn = self
+            .1
+            .queue(Into::<MessageId>::into(complement_id.clone()).into())
+            .await;
+
+        let policy = match scoped_policy() {
+            Some(policy) => policy,
+            None => self.2.read().await.clone(),
+        };
+
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .request_inner(msg.clone(), complement_id.clone(), policy.timeout())
+                .await;
+            match result {
+                Err(_) if attempt < policy.attempts => {
+                    tokio::time::sleep(policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => {
+                    record_request(attempt);
+                    if attempt > 1 {
+                        info!(
+                            "Request to device {} took {} attempts",
+                            msg.device_id, attempt
+                        );
+                    }
+                    return result.map_err(|e| match e.is::<tokio::time::error::Elapsed>() {
+                        true => e.context(format!(
+                            "Timed out waiting for response after {} attempts",
+                            attempt
+                        )),
+                        false => e,
+                    });
+                }
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum DeviceType {
     Grapple(GrappleModelId),
     RoboRIO,
@@ -146,6 +185,21 @@ pub trait Device: RpcBase {
 #[async_trait::async_trait]
 pub trait RootDevice: Device {
     fn device_class(&self) -> &'static str;
+
+    /// The device-specific settings to include in a configuration snapshot, if it has any.
+    async fn snapshot_config(&self) -> anyhow::Result<Option<DeviceConfig>> {
+        Ok(None)
+    }
+
+    async fn apply_config(&self, _config: &DeviceConfig) -> anyhow::Result<()> {
+        anyhow::bail!(
+            "{} does not support applying configuration",
+            self.device_class()
+        )
+    }
+
+    /// Called when the device answers enumeration again after having gone offline
+    async fn back_online(&self) {}
 }
 
 pub type SharedInfo = Arc<RwLock<DeviceInfo>>;
@@ -232,10 +286,84 @@ impl Device for GrappleDevice {}
 
 /* FIRMWARE UPGRADE DEVICE */
 
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "type", content = "data")]
+pub enum FirmwareUpgradeError {
+    /// The device didn't acknowledge a chunk in time
+    Timeout {
+        chunk: usize,
+        offset: u32,
+    },
+    /// The device rejected a chunk
+    Nack {
+        chunk: usize,
+        offset: u32,
+        error: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for FirmwareUpgradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FirmwareUpgradeError::Timeout { chunk, offset } => write!(
+                f,
+                "Timed out waiting for the device to acknowledge chunk {} (offset {})",
+                chunk, offset
+            ),
+            FirmwareUpgradeError::Nack {
+                chunk,
+                offset,
+                error,
+            } => write!(
+                f,
+                "Device rejected chunk {} (offset {}): {}",
+                chunk, offset, error
+            ),
+            FirmwareUpgradeError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FirmwareUpgradeError {}
+
+impl FirmwareUpgradeError {
+    pub fn from_error(e: &anyhow::Error) -> Self {
+        e.downcast_ref::<FirmwareUpgradeError>()
+            .cloned()
+            .unwrap_or(FirmwareUpgradeError::Other {
+                message: e.to_string(),
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "result", content = "data")]
+pub enum FirmwareUpgradeResult {
+    Success,
+    Failed { error: FirmwareUpgradeError },
+}
+
+impl FirmwareUpgradeResult {
+    pub fn from_result(result: &anyhow::Result<()>) -> Self {
+        match result {
+            Ok(()) => FirmwareUpgradeResult::Success,
+            Err(e) => FirmwareUpgradeResult::Failed {
+                error: FirmwareUpgradeError::from_error(e),
+            },
+        }
+    }
+}
+
 pub struct FirmwareUpgradeDevice<T: FirmwareValidatingDevice> {
     sender: SendWrapper,
     info: SharedInfo,
     progress: Arc<RwLock<Option<f64>>>,
+    /// Outcome of the last upgrade. Mostly of interest on failure, since on success the device
+    /// reboots out of DFU and this device goes away.
+    result: Arc<RwLock<Option<FirmwareUpgradeResult>>>,
     ack: Arc<Notify>,
     chunk_size: usize,
     _t: PhantomData<T>,
@@ -247,121 +375,220 @@ impl<T: FirmwareValidatingDevice> FirmwareUpgradeDevice<T> {
             sender,
             info,
             progress: Arc::new(RwLock::new(None)),
+            result: Arc::new(RwLock::new(None)),
             ack: Arc::new(Notify::new()),
             chunk_size,
             _t: PhantomData,
         }
     }
 
-    pub async fn field_upgrade_worker(
-        sender: SendWrapper,
-        id: u8,
-        data: &[u8],
-        progress: Arc<RwLock<Option<f64>>>,
-        ack: Arc<Notify>,
-        chunk_size: usize,
-    ) -> anyhow::Result<()> {
-        *progress.write().await = Some(0.0);
+    async fn spawn_upgrade(&self, firmware: Arc<Vec<u8>>) -> anyhow::Result<()> {
+        let info = self.info.read().await.clone();
+        let id = info.require_device_id()?;
+        let serial = info.require_serial()?;
+        if upgrade_checkpoint::is_active(serial) {
+            anyhow::bail!("A firmware upgrade is already running on this device");
+        }
 
-        // Try to check for V2 firmware upgrade
-        let (encode, decode) = request_factory!(
-            dat2,
-            GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::GetFlashParameters(dat2))
-        );
+        let sender = self.sender.clone();
+        let progress = self.progress.clone();
+        let result = self.result.clone();
+        *result.write().await = None;
+        let notify = self.ack.clone();
+        let chunk_size = self.chunk_size;
 
-        let msg = sender
-            .request(TaggedGrappleMessage::new(id, encode(())), 300, 5)
+        tokio::task::spawn(async move {
+            let r = field_upgrade_worker(
+                sender,
+                id,
+                serial,
+                firmware,
+                info.is_dfu_in_progress,
+                progress.clone(),
+                notify,
+                chunk_size,
+            )
             .await;
+            info!("Field upgrade of {} finished: {:?}", serial, r);
+            if r.is_err() {
+                *progress.write().await = None;
+            }
+            *result.write().await = Some(FirmwareUpgradeResult::from_result(&r));
+        });
+        Ok(())
+    }
 
-        let mut flash_params = FlashParameters {
-            flash_compat_version: 1,
-            align: chunk_size as u16,
-            payload_len: chunk_size as u16,
-        };
-        println!("{:?}", msg);
+    /// If the device was part way through an upgrade we have a checkpoint for, pick it back up.
+    pub async fn resume_if_interrupted(self) -> Self {
+        self.resume_interrupted().await;
+        self
+    }
 
-        match msg {
-            Ok(msg) => {
-                let result = decode(msg.msg).ok().map(|x| x.ok()).flatten();
-                if let Some(params) = result {
-                    flash_params = params
+    async fn resume_interrupted(&self) {
+        let info = self.info.read().await.clone();
+        if let (true, Some(serial)) = (info.is_dfu_in_progress, info.serial) {
+            if let Some(checkpoint) = upgrade_checkpoint::get(serial) {
+                if upgrade_checkpoint::is_active(serial) {
+                    return;
+                }
+                if let Err(e) = self.spawn_upgrade(checkpoint.firmware).await {
+                    warn!("Could not resume field upgrade of {}: {}", serial, e);
                 }
             }
-            // Default to v1
-            Err(_) => {}
         }
+    }
+}
 
-        let chunks = data.chunks(flash_params.payload_len as usize);
-        let nchunks = chunks.len();
-        for (i, chunk) in chunks.enumerate() {
-            info!("Chunk {} of {} (len: {})", i, nchunks, chunk.len());
-
-            let mut padded = vec![0u8; flash_params.payload_len as usize];
-
-            for i in 0..chunk.len() {
-                padded[i] = chunk[i];
+/// Flash firmware to a device in DFU. If `resume` is set and a checkpoint exists for the same
+/// firmware, chunks the device has already acknowledged are skipped. Only V2 upgrades can be
+/// resumed, since V1 parts carry no offset.
+pub async fn field_upgrade_worker(
+    sender: SendWrapper,
+    id: u8,
+    serial: u32,
+    data: Arc<Vec<u8>>,
+    resume: bool,
+    progress: Arc<RwLock<Option<f64>>>,
+    ack: Arc<Notify>,
+    chunk_size: usize,
+) -> anyhow::Result<()> {
+    let _active = ActiveUpgrade::acquire(serial)?;
+    *progress.write().await = Some(0.0);
+
+    // Try to check for V2 firmware upgrade
+    let (encode, decode) = request_factory!(
+        dat2,
+        GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::GetFlashParameters(dat2))
+    );
+
+    let msg = sender
+        .request(TaggedGrappleMessage::new(id, encode(())))
+        .await;
+
+    let mut flash_params = FlashParameters {
+        flash_compat_version: 1,
+        align: chunk_size as u16,
+        payload_len: chunk_size as u16,
+    };
+    debug!("Flash parameters response: {:?}", msg);
+
+    match msg {
+        Ok(msg) => {
+            let result = decode(msg.msg).ok().map(|x| x.ok()).flatten();
+            if let Some(params) = result {
+                flash_params = params
             }
+        }
+        // Default to v1
+        Err(_) => {}
+    }
 
-            match flash_params.flash_compat_version {
-                0 => {}
-                1 => {
-                    sender
-                        .send(TaggedGrappleMessage::new(
-                            id,
-                            GrappleDeviceMessage::FirmwareUpdate(
-                                GrappleFirmwareMessage::UpdatePart(
-                                    AsymmetricCow(Cow::<Payload>::Borrowed(Into::into(
-                                        &padded[..],
-                                    )))
-                                    .into_static(),
-                                ),
-                            ),
-                        ))
-                        .await?;
-                    tokio::time::timeout(Duration::from_millis(1000), ack.notified()).await?;
-                }
-                2.. => {
-                    let (part_encode, part_decode) = request_factory!(
-                        dat2,
-                        GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::UpdatePartV2(
-                            dat2
-                        ))
-                    );
-
-                    let msg = sender
-                        .request(
-                            TaggedGrappleMessage::new(
-                                id,
-                                part_encode(UpdatePartV2Payload {
-                                    offset: i as u32 * flash_params.payload_len as u32,
-                                    payload: Cow::<Payload>::Borrowed(Into::into(&padded[..]))
-                                        .into_static()
-                                        .into(),
-                                }),
-                            ),
-                            300,
-                            5,
-                        )
-                        .await?;
-
-                    part_decode(msg.msg)??;
-                }
-            }
+    let start_offset = match upgrade_checkpoint::get(serial) {
+        Some(checkpoint)
+            if resume
+                && flash_params.flash_compat_version >= 2
+                && checkpoint.payload_len == flash_params.payload_len
+                && checkpoint.firmware == data =>
+        {
+            info!(
+                "Resuming field upgrade of {} from offset {}",
+                serial, checkpoint.next_offset
+            );
+            checkpoint.next_offset
+        }
+        _ => 0,
+    };
+
+    let chunks = data.chunks(flash_params.payload_len as usize);
+    let nchunks = chunks.len();
+    for (i, chunk) in chunks.enumerate() {
+        let offset = i as u32 * flash_params.payload_len as u32;
+        if offset < start_offset {
+            continue;
+        }
+        info!("Chunk {} of {} (len: {})", i, nchunks, chunk.len());
 
-            *progress.write().await = Some((i + 1) as f64 / (nchunks as f64) * 100.0);
+        let mut padded = vec![0u8; flash_params.payload_len as usize];
+
+        for i in 0..chunk.len() {
+            padded[i] = chunk[i];
         }
 
-        *progress.write().await = Some(100.0);
-        sender
-            .send(TaggedGrappleMessage::new(
-                id,
-                GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::UpdateDone),
-            ))
-            .await?;
-        *progress.write().await = None;
+        match flash_params.flash_compat_version {
+            0 => {}
+            1 => {
+                sender
+                    .send(TaggedGrappleMessage::new(
+                        id,
+                        GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::UpdatePart(
+                            AsymmetricCow(Cow::<Payload>::Borrowed(Into::into(&padded[..])))
+                                .into_static(),
+                        )),
+                    ))
+                    .await?;
+                tokio::time::timeout(Duration::from_millis(1000), ack.notified())
+                    .await
+                    .map_err(|_| FirmwareUpgradeError::Timeout { chunk: i, offset })?;
+            }
+            2.. => {
+                let (part_encode, part_decode) = request_factory!(
+                    dat2,
+                    GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::UpdatePartV2(
+                        dat2
+                    ))
+                );
+
+                let msg = sender
+                    .request(TaggedGrappleMessage::new(
+                        id,
+                        part_encode(UpdatePartV2Payload {
+                            offset,
+                            payload: Cow::<Payload>::Borrowed(Into::into(&padded[..]))
+                                .into_static()
+                                .into(),
+                        }),
+                    ))
+                    .await
+                    .map_err(|e| match e.downcast_ref::<tokio::time::error::Elapsed>() {
+                        Some(_) => FirmwareUpgradeError::Timeout { chunk: i, offset },
+                        None => FirmwareUpgradeError::Other {
+                            message: e.to_string(),
+                        },
+                    })?;
+
+                part_decode(msg.msg)
+                    .and_then(|x| x)
+                    .map_err(|e| FirmwareUpgradeError::Nack {
+                        chunk: i,
+                        offset,
+                        error: e.to_string(),
+                    })?;
+
+                upgrade_checkpoint::set(
+                    serial,
+                    UpgradeCheckpoint {
+                        firmware: data.clone(),
+                        payload_len: flash_params.payload_len,
+                        next_offset: offset + flash_params.payload_len as u32,
+                    },
+                );
+            }
+        }
 
-        Ok(())
+        *progress.write().await = Some((i + 1) as f64 / (nchunks as f64) * 100.0);
     }
+
+    *progress.write().await = Some(100.0);
+    sender
+        .send(TaggedGrappleMessage::new(
+            id,
+            GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::UpdateDone),
+        ))
+        .await?;
+    upgrade_checkpoint::clear(serial);
+    *progress.write().await = None;
+
+    Ok(())
 }
 
 pub async fn start_field_upgrade(sender: &SendWrapper, serial: u32) -> anyhow::Result<()> {
@@ -375,27 +602,42 @@ pub async fn start_field_upgrade(sender: &SendWrapper, serial: u32) -> anyhow::R
         .await
 }
 
-fn maybe_unpack_firmware(data: &[u8]) -> anyhow::Result<Vec<u8>> {
-    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
-
-    // TODO: Use https://github.com/GrappleRobotics/bundle/tree/master/grapple-bundle-lib
-
-    let mut index_file = archive.by_name("index")?;
-    let mut index_content = String::new();
-    index_file.read_to_string(&mut index_content)?;
-    let index: serde_json::Value = serde_json::from_str(&index_content)?;
-    drop(index_file);
+/// Firmware ready to be flashed to a device
+pub struct FirmwareImage {
+    pub data: Vec<u8>,
+    /// The version declared by the bundle the firmware came from, if any
+    pub version: Option<String>,
+}
 
-    let mut firmware_f = archive.by_name(
-        index
-            .get("firmware_update_bin")
-            .map(|x| x.as_str())
-            .flatten()
-            .ok_or(anyhow::anyhow!("Invalid Index"))?,
-    )?;
-    let mut v = vec![];
-    firmware_f.read_to_end(&mut v)?;
-    Ok(v)
+/// Unpack the firmware for this model from a bundle, or validate a raw firmware binary, ready to
+/// be flashed. Bundles are checked against the checksums and version declared in their index.
+pub fn prepare_firmware<T: FirmwareValidatingDevice>(
+    info: &DeviceInfo,
+    data: Vec<u8>,
+) -> anyhow::Result<FirmwareImage> {
+    match FirmwareBundle::open(&data)? {
+        Some(mut bundle) => {
+            let (buf, version) = bundle.firmware_for(&T::model_id(info)?)?;
+            if let Some(version) = &version {
+                T::validate_firmware_version(firmware_bundle::normalise_version(version))
+                    .map_err(|e| anyhow::anyhow!("Unsupported firmware version: {}", e))?;
+            }
+            T::validate_firmware(info, &buf)
+                .map_err(|e| anyhow::anyhow!("Not a valid firmware file: {}", e))?;
+            Ok(FirmwareImage { data: buf, version })
+        }
+        None if !T::accepts_raw_firmware() => anyhow::bail!(
+            "This device can only be upgraded with a firmware bundle, not a raw firmware binary"
+        ),
+        None => {
+            T::validate_firmware(info, &data)
+                .map_err(|e| anyhow::anyhow!("Not a valid firmware file: {}", e))?;
+            Ok(FirmwareImage {
+                data,
+                version: None,
+            })
+        }
+    }
 }
 
 #[rpc]
@@ -403,46 +645,62 @@ impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync>
     FirmwareUpgradeDevice<T>
 {
     async fn do_field_upgrade(&self, data: Vec<u8>) -> anyhow::Result<()> {
-        let buf = match maybe_unpack_firmware(&data) {
-            Ok(buf) => buf,
-            Err(_) => {
-                let d = data;
-                <T>::validate_firmware(&*self.info.read().await, &d)
-                    .map_err(|e| anyhow::anyhow!("Not a valid firmware file: {}", e))?;
-                d
-            }
-        };
+        let image = prepare_firmware::<T>(&*self.info.read().await, data)?;
+        self.spawn_upgrade(Arc::new(image.data)).await
+    }
 
-        let sender = self.sender.clone();
-        let progress = self.progress.clone();
-        let id = self.info.read().await.require_device_id()?;
-        let notify = self.ack.clone();
-        let chunk_size = self.chunk_size;
+    /// Download this device's firmware from a release (or the firmware cache) and flash it
+    async fn do_field_upgrade_from_release(
+        &self,
+        release: LightReleaseResponse,
+    ) -> anyhow::Result<()> {
+        let model = T::model_id(&*self.info.read().await)?;
+        let asset = choose_firmware_asset(&release, &model).ok_or(anyhow::anyhow!(
+            "Release {} doesn't contain firmware for this device",
+            release.tag_name
+        ))?;
+        let data = download_asset(&release, asset).await?;
+        self.do_field_upgrade(data).await
+    }
 
-        tokio::task::spawn(async move {
-            let d = buf;
-            let r =
-                Self::field_upgrade_worker(sender, id, &d[..], progress, notify, chunk_size).await;
-            println!("FW Update Result: {:?}", r);
-        });
-        Ok(())
+    async fn resume_field_upgrade(&self) -> anyhow::Result<()> {
+        let serial = self.info.read().await.require_serial()?;
+        let checkpoint = upgrade_checkpoint::get(serial)
+            .ok_or(anyhow::anyhow!("No interrupted upgrade to resume"))?;
+        self.spawn_upgrade(checkpoint.firmware).await
+    }
+
+    async fn can_resume_field_upgrade(&self) -> anyhow::Result<bool> {
+        let info = self.info.read().await;
+        Ok(info.is_dfu_in_progress && upgrade_checkpoint::get(info.require_serial()?).is_some())
     }
 
     async fn progress(&self) -> anyhow::Result<Option<f64>> {
         Ok(self.progress.read().await.clone())
     }
 
+    async fn upgrade_result(&self) -> anyhow::Result<Option<FirmwareUpgradeResult>> {
+        Ok(self.result.read().await.clone())
+    }
+
     async fn get_firmware_url(&self) -> anyhow::Result<Option<String>> {
         Ok(T::firmware_url())
     }
 }
 
+#[async_trait::async_trait]
 impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> RootDevice
     for FirmwareUpgradeDevice<T>
 {
     fn device_class(&self) -> &'static str {
         "GrappleFirmwareUpgrade"
     }
+
+    // A device that reboots part way through an upgrade keeps its entry while it's offline, so
+    // it's only picked up again here rather than when it's created.
+    async fn back_online(&self) {
+        self.resume_interrupted().await;
+    }
 }
 
 #[async_trait::async_trait]
@@ -472,7 +730,20 @@ pub trait HasFirmwareUpdateURLDevice {
 }
 
 pub trait FirmwareValidatingDevice {
+    fn model_id(info: &DeviceInfo) -> anyhow::Result<GrappleModelId>;
     fn validate_firmware(info: &DeviceInfo, buf: &[u8]) -> anyhow::Result<()>;
+
+    /// Whether raw firmware binaries can be flashed, or only bundles (which declare the model
+    /// they're for).
+    fn accepts_raw_firmware() -> bool {
+        true
+    }
+
+    /// Check that GrappleHook will still be able to talk to the device once it's running
+    /// firmware of the given version.
+    fn validate_firmware_version(_version: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -482,6 +753,11 @@ pub trait VersionGatedDevice:
     fn validate_version(version: Option<String>) -> anyhow::Result<()>;
     async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>;
 
+    /// The repository firmware for this device is released from, e.g. `GrappleRobotics/LaserCAN`
+    fn release_repo() -> &'static str;
+    /// The tag of the release for the given firmware version
+    fn release_tag(version: &Version) -> String;
+
     fn require_version(version: Option<String>, req: &str) -> anyhow::Result<()> {
         if let Some(v) = version {
             let v = Version::parse(&v)?;
@@ -574,3 +850,69 @@ impl OldVersionDevice {
         self.grapple_device.rpc_process(msg).await
     }
 }
+
+/// A Grapple device of a model this version of GrappleHook doesn't know about. It can still be
+/// identified, renamed, re-IDed and upgraded, but nothing model-specific.
+pub struct UnknownGrappleDevice {
+    grapple_device: GrappleDevice,
+}
+
+impl UnknownGrappleDevice {
+    pub fn new(sender: SendWrapper, info: SharedInfo) -> Self {
+        Self {
+            grapple_device: GrappleDevice::new(sender, info),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Device for UnknownGrappleDevice {
+    async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+        self.grapple_device.handle(msg.clone()).await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl RootDevice for UnknownGrappleDevice {
+    fn device_class(&self) -> &'static str {
+        "UnknownGrappleDevice"
+    }
+}
+
+impl HasFirmwareUpdateURLDevice for UnknownGrappleDevice {
+    fn firmware_url() -> Option<String> {
+        None
+    }
+}
+
+impl FirmwareValidatingDevice for UnknownGrappleDevice {
+    fn model_id(info: &DeviceInfo) -> anyhow::Result<GrappleModelId> {
+        match &info.device_type {
+            DeviceType::Grapple(model) => Ok(model.clone()),
+            t => anyhow::bail!("{:?} is not a Grapple device", t),
+        }
+    }
+
+    // We don't know where this model keeps its firmware header, so rely on the bundle index to
+    // say which model the firmware is for.
+    fn validate_firmware(_info: &DeviceInfo, _buf: &[u8]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn accepts_raw_firmware() -> bool {
+        false
+    }
+}
+
+#[rpc]
+impl UnknownGrappleDevice {
+    async fn start_field_upgrade(&self) -> anyhow::Result<()> {
+        let serial = self.grapple_device.info.read().await.require_serial()?;
+        start_field_upgrade(&self.grapple_device.sender, serial).await
+    }
+
+    async fn grapple(&self, msg: GrappleDeviceRequest) -> anyhow::Result<GrappleDeviceResponse> {
+        self.grapple_device.rpc_process(msg).await
+    }
+}
@@ -1,8 +1,12 @@
 use grapple_hook_macros::rpc;
+use tokio::sync::broadcast;
 
 use crate::rpc::RpcBase;
 
-use super::device_manager::{DeviceManagerRequest, DeviceManagerResponse};
+use super::{
+    device_manager::{DeviceManagerRequest, DeviceManagerResponse},
+    events::DeviceEvent,
+};
 
 #[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct ProviderInfo {
@@ -17,6 +21,7 @@ pub trait DeviceProvider {
     async fn connect(&self) -> anyhow::Result<()>;
     async fn disconnect(&self) -> anyhow::Result<()>;
     async fn info(&self) -> anyhow::Result<ProviderInfo>;
+    fn subscribe(&self) -> broadcast::Receiver<DeviceEvent>;
 
     async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value>;
     async fn device_manager_call(
@@ -33,6 +38,10 @@ impl WrappedDeviceProvider {
     pub fn new(inner: Box<dyn DeviceProvider + Send + Sync>) -> Self {
         Self { inner }
     }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.inner.subscribe()
+    }
 }
 
 #[rpc]
@@ -2,17 +2,27 @@ use std::collections::HashMap;
 
 use grapple_hook_macros::rpc;
 use network_interface::{NetworkInterface, NetworkInterfaceConfig};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 
+#[cfg(target_os = "linux")]
+use super::socketcan::SocketCan;
 use super::{
+    events::{forward_events, ProviderDeviceEvent, EVENT_CAPACITY},
+    firmware_bundle::{import_bundles, ImportedFirmwareBundle},
     generic_usb::GenericUSB,
     provider::{
         DeviceProvider, ProviderInfo, WrappedDeviceProvider, WrappedDeviceProviderRequest,
         WrappedDeviceProviderResponse,
     },
+    replay::Replay,
     roborio::daemon::RoboRioDaemon,
+    simulator::Simulator,
+};
+use crate::{
+    canlog::RecordingFormat,
+    rpc::RpcBase,
+    updates::{self, UpdateSourceConfig},
 };
-use crate::rpc::RpcBase;
 
 pub struct ProviderContainer {
     provider: WrappedDeviceProvider,
@@ -20,29 +30,56 @@ pub struct ProviderContainer {
     last_autodetect: std::time::Instant,
 }
 
+impl ProviderContainer {
+    fn new(
+        address: &str,
+        provider: WrappedDeviceProvider,
+        is_autodetect: bool,
+        events: &broadcast::Sender<ProviderDeviceEvent>,
+    ) -> Self {
+        forward_events(address.to_owned(), provider.subscribe(), events.clone());
+        Self {
+            provider,
+            is_autodetect,
+            last_autodetect: std::time::Instant::now(),
+        }
+    }
+}
+
 pub struct ProviderManager {
     providers: RwLock<HashMap<String, ProviderContainer>>,
     last_detect: RwLock<std::time::Instant>,
+    events: broadcast::Sender<ProviderDeviceEvent>,
 }
 
 impl ProviderManager {
     pub async fn new() -> Self {
+        let events = broadcast::channel(EVENT_CAPACITY).0;
+
         let mut hm = HashMap::new();
         let rr = RoboRioDaemon::new();
+        let address = rr.info().await.unwrap().address;
         hm.insert(
-            rr.info().await.unwrap().address,
-            ProviderContainer {
-                provider: WrappedDeviceProvider::new(Box::new(rr)),
-                is_autodetect: false,
-                last_autodetect: std::time::Instant::now(),
-            },
+            address.clone(),
+            ProviderContainer::new(
+                &address,
+                WrappedDeviceProvider::new(Box::new(rr)),
+                false,
+                &events,
+            ),
         );
         Self {
             providers: RwLock::new(hm),
             last_detect: RwLock::new(std::time::Instant::now()),
+            events,
         }
     }
 
+    /// Device events from every provider, including ones added after subscribing
+    pub fn subscribe(&self) -> broadcast::Receiver<ProviderDeviceEvent> {
+        self.events.subscribe()
+    }
+
     pub fn interfaces() -> Vec<NetworkInterface> {
         NetworkInterface::show().unwrap_or(vec![])
     }
@@ -63,13 +100,14 @@ impl ProviderManager {
                                 if !providers.contains_key(&addr) {
                                     providers.insert(
                                         addr.clone(),
-                                        ProviderContainer {
-                                            provider: WrappedDeviceProvider::new(Box::new(
-                                                GenericUSB::new(addr),
-                                            )),
-                                            is_autodetect: true,
-                                            last_autodetect: now,
-                                        },
+                                        ProviderContainer::new(
+                                            &addr,
+                                            WrappedDeviceProvider::new(Box::new(GenericUSB::new(
+                                                addr.clone(),
+                                            ))),
+                                            true,
+                                            &self.events,
+                                        ),
                                     );
                                 } else {
                                     providers.get_mut(&addr).unwrap().last_autodetect = now;
@@ -81,6 +119,24 @@ impl ProviderManager {
                 }
             }
 
+            // Look for SocketCAN interfaces
+            #[cfg(target_os = "linux")]
+            for iface in SocketCan::interfaces() {
+                if !providers.contains_key(&iface) {
+                    providers.insert(
+                        iface.clone(),
+                        ProviderContainer::new(
+                            &iface,
+                            WrappedDeviceProvider::new(Box::new(SocketCan::new(iface.clone()))),
+                            true,
+                            &self.events,
+                        ),
+                    );
+                } else {
+                    providers.get_mut(&iface).unwrap().last_autodetect = now;
+                }
+            }
+
             *self.last_detect.write().await = now;
         }
 
@@ -112,6 +168,58 @@ impl ProviderManager {
         Ok(())
     }
 
+    async fn add_replay(&self, path: String, format: RecordingFormat) -> anyhow::Result<String> {
+        let replay = Replay::new(path, format);
+        let address = replay.address();
+        let mut providers = self.providers.write().await;
+        if providers.contains_key(&address) {
+            anyhow::bail!("There is already a provider with address {}", address);
+        }
+        providers.insert(
+            address.clone(),
+            ProviderContainer::new(
+                &address,
+                WrappedDeviceProvider::new(Box::new(replay)),
+                false,
+                &self.events,
+            ),
+        );
+        Ok(address)
+    }
+
+    async fn add_simulator(&self) -> anyhow::Result<String> {
+        let address = "simulator".to_owned();
+        let mut providers = self.providers.write().await;
+        if !providers.contains_key(&address) {
+            providers.insert(
+                address.clone(),
+                ProviderContainer::new(
+                    &address,
+                    WrappedDeviceProvider::new(Box::new(Simulator::new(address.clone()))),
+                    false,
+                    &self.events,
+                ),
+            );
+        }
+        Ok(address)
+    }
+
+    async fn update_source(&self) -> anyhow::Result<UpdateSourceConfig> {
+        Ok(updates::update_source())
+    }
+
+    async fn set_update_source(&self, config: UpdateSourceConfig) -> anyhow::Result<()> {
+        updates::set_update_source(config)
+    }
+
+    /// Import a folder of firmware bundles into the firmware cache, for use without internet
+    async fn import_firmware_bundles(
+        &self,
+        path: String,
+    ) -> anyhow::Result<Vec<ImportedFirmwareBundle>> {
+        import_bundles(&path).await
+    }
+
     async fn provider(
         &self,
         address: String,
@@ -121,7 +229,7 @@ impl ProviderManager {
             .read()
             .await
             .get(&address)
-            .unwrap()
+            .ok_or(anyhow::anyhow!("No provider with address {}", address))?
             .provider
             .rpc_process(msg)
             .await
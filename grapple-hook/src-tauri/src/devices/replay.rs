@@ -0,0 +1,323 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bounded_static::ToBoundedStatic;
+use grapple_frc_msgs::{
+    binmarshal::{BitView, Demarshal},
+    bridge::BridgedCANMessage,
+    grapple::{
+        fragments::{FragmentReassembler, FragmentReassemblerRx},
+        TaggedGrappleMessage,
+    },
+    ManufacturerMessage,
+};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify, RwLock};
+
+use crate::{
+    canlog::{read_recording, RecordedMessage, RecordingFormat},
+    rpc::RpcBase,
+};
+
+use super::{
+    device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse},
+    events::DeviceEvent,
+    provider::{DeviceProvider, ProviderInfo},
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum ReplayMode {
+    RealTime,
+    Accelerated { speed: f64 },
+    Step,
+}
+
+impl ReplayMode {
+    fn speed(&self) -> Option<f64> {
+        match self {
+            ReplayMode::RealTime => Some(1.0),
+            ReplayMode::Accelerated { speed } => Some(*speed),
+            ReplayMode::Step => None,
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ReplayStatus {
+    pub mode: ReplayMode,
+    pub position: usize,
+    pub total: usize,
+}
+
+pub struct ReplayInner {
+    path: String,
+    format: RecordingFormat,
+    running: AtomicBool,
+    device_manager: DeviceManager,
+
+    stop_signal_tx: mpsc::Sender<()>,
+    stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+    step_tx: mpsc::Sender<usize>,
+    step_rx: Mutex<mpsc::Receiver<usize>>,
+
+    send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+    mode: RwLock<ReplayMode>,
+    mode_changed: Notify,
+    position: AtomicUsize,
+    total: AtomicUsize,
+}
+
+/// Replays a recorded CAN capture into a DeviceManager, as if it were a live bus.
+pub struct Replay {
+    inner: Arc<ReplayInner>,
+}
+
+impl Replay {
+    pub fn new(path: String, format: RecordingFormat) -> Self {
+        let (send_tx, send_rx) = mpsc::channel(100);
+        let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+        let (step_tx, step_rx) = mpsc::channel(5);
+
+        let mut sends = HashMap::new();
+        sends.insert("CAN".to_owned(), send_tx);
+
+        Self {
+            inner: Arc::new(ReplayInner {
+                path,
+                format,
+                running: AtomicBool::new(false),
+                device_manager: DeviceManager::new(sends),
+                stop_signal_tx,
+                stop_signal_rx: Mutex::new(stop_signal_rx),
+                step_tx,
+                step_rx: Mutex::new(step_rx),
+                send_rx: Mutex::new(send_rx),
+                mode: RwLock::new(ReplayMode::RealTime),
+                mode_changed: Notify::new(),
+                position: AtomicUsize::new(0),
+                total: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    pub fn address(&self) -> String {
+        format!("replay:{}", self.inner.path)
+    }
+
+    async fn replay_one(
+        inner: &ReplayInner,
+        reassemble_rx: &mut FragmentReassemblerRx,
+        msg: &BridgedCANMessage<'static>,
+    ) -> anyhow::Result<()> {
+        let manufacturer_msg =
+            ManufacturerMessage::read(&mut BitView::new(&msg.data.0[..]), msg.id);
+        match manufacturer_msg {
+            Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
+                let mut storage = Vec::new();
+                if let Ok(Some((gid, grpl_unfragmented))) =
+                    reassemble_rx.defragment(msg.timestamp as i64, &msg.id, grpl_msg, &mut storage)
+                {
+                    inner
+                        .device_manager
+                        .on_message(
+                            "CAN".to_owned(),
+                            gid,
+                            TaggedGrappleMessage::new(
+                                msg.id.device_id,
+                                grpl_unfragmented.to_static(),
+                            ),
+                        )
+                        .await?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    async fn do_loop(
+        messages: Vec<RecordedMessage>,
+        inner: Arc<ReplayInner>,
+    ) -> anyhow::Result<()> {
+        let mut send_rx = inner
+            .send_rx
+            .try_lock()
+            .map_err(|_| anyhow::anyhow!("This Replay is already running!"))?;
+        let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+        let mut step_rx = inner.step_rx.try_lock()?;
+
+        let (mut reassemble_rx, _) = FragmentReassembler::new(1000, 8).split();
+        let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+
+        let mut idx = 0;
+        // Position in the recording. Advances with wall time (scaled by the speed) in timed modes,
+        // and jumps to each message in step mode.
+        let mut replay_clock = Duration::ZERO;
+        let mut last_real = Instant::now();
+
+        loop {
+            let speed = inner.mode.read().await.speed();
+            if let Some(speed) = speed {
+                replay_clock += last_real.elapsed().mul_f64(speed);
+            }
+            last_real = Instant::now();
+
+            if speed.is_some() {
+                while let Some((offset, msg)) = messages.get(idx) {
+                    if *offset > replay_clock {
+                        break;
+                    }
+                    Self::replay_one(&inner, &mut reassemble_rx, msg).await?;
+                    idx += 1;
+                }
+                inner
+                    .position
+                    .store(idx, std::sync::atomic::Ordering::Relaxed);
+            }
+
+            let wait = match (speed, messages.get(idx)) {
+                (Some(speed), Some((offset, _))) => {
+                    Some(offset.saturating_sub(replay_clock).div_f64(speed))
+                }
+                _ => None,
+            };
+
+            tokio::select! {
+              _ = tokio::time::sleep(wait.unwrap_or_default()), if wait.is_some() => (),
+              _ = inner.mode_changed.notified() => (),
+              n = step_rx.recv() => match n {
+                Some(n) if speed.is_none() => {
+                  for _ in 0..n {
+                    if let Some((offset, msg)) = messages.get(idx) {
+                      Self::replay_one(&inner, &mut reassemble_rx, msg).await?;
+                      replay_clock = *offset;
+                      idx += 1;
+                    }
+                  }
+                  inner.position.store(idx, std::sync::atomic::Ordering::Relaxed);
+                },
+                _ => ()
+              },
+              // Nothing is listening on the other end, so anything the devices send is dropped.
+              _ = send_rx.recv() => (),
+              sig = stop_signal_rx.recv() => match sig {
+                Some(()) => {
+                  break;
+                },
+                None => ()
+              },
+              // Once the replay is paused or complete, stop ticking so devices aren't aged off.
+              _ = device_manager_interval.tick(), if speed.is_some() && idx < messages.len() => {
+                inner.device_manager.on_tick().await?;
+              }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn do_start(inner: Arc<ReplayInner>) -> anyhow::Result<()> {
+        info!("Loading replay {}...", inner.path);
+
+        let messages = read_recording(&inner.path, &inner.format)?;
+        inner
+            .total
+            .store(messages.len(), std::sync::atomic::Ordering::Relaxed);
+
+        info!("Loaded {} messages", messages.len());
+
+        tokio::task::spawn(async move {
+            inner
+                .running
+                .store(true, std::sync::atomic::Ordering::Relaxed);
+            let r = Self::do_loop(messages, inner.clone()).await;
+            inner.device_manager.reset().await;
+            inner
+                .running
+                .store(false, std::sync::atomic::Ordering::Relaxed);
+            match r {
+                Ok(_) => info!("Replay runner stopped gracefully"),
+                Err(e) => warn!("Replay runner stopped with error: {}", e),
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for Replay {
+    async fn connect(&self) -> anyhow::Result<()> {
+        Self::do_start(self.inner.clone()).await
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        self.inner.stop_signal_tx.send(()).await.ok();
+        Ok(())
+    }
+
+    async fn info(&self) -> anyhow::Result<ProviderInfo> {
+        Ok(ProviderInfo {
+            ty: "Replay".to_owned(),
+            description: format!("Replay of {}", self.inner.path),
+            address: self.address(),
+            connected: self
+                .inner
+                .running
+                .load(std::sync::atomic::Ordering::Relaxed),
+        })
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.inner.device_manager.subscribe()
+    }
+
+    async fn device_manager_call(
+        &self,
+        req: DeviceManagerRequest,
+    ) -> anyhow::Result<DeviceManagerResponse> {
+        self.inner.device_manager.rpc_process(req).await
+    }
+
+    async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        self.rpc_call(req).await
+    }
+}
+
+#[rpc]
+impl Replay {
+    async fn status(&self) -> anyhow::Result<ReplayStatus> {
+        Ok(ReplayStatus {
+            mode: self.inner.mode.read().await.clone(),
+            position: self
+                .inner
+                .position
+                .load(std::sync::atomic::Ordering::Relaxed),
+            total: self.inner.total.load(std::sync::atomic::Ordering::Relaxed),
+        })
+    }
+
+    async fn set_mode(&self, mode: ReplayMode) -> anyhow::Result<()> {
+        if let ReplayMode::Accelerated { speed } = mode {
+            if !(speed > 0.0) {
+                anyhow::bail!("Replay speed must be positive");
+            }
+        }
+        *self.inner.mode.write().await = mode;
+        self.inner.mode_changed.notify_one();
+        Ok(())
+    }
+
+    async fn step(&self, count: usize) -> anyhow::Result<()> {
+        self.inner.step_tx.send(count).await?;
+        Ok(())
+    }
+}
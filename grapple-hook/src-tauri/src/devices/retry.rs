@@ -0,0 +1,127 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How requests to devices are retried when they go unanswered.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct RetryPolicy {
+    /// How long to wait for each reply
+    pub timeout_ms: u64,
+    /// How many times to send the request, including the first
+    pub attempts: u32,
+    /// Delay before the first retry. Doubles for each retry after that, up to `max_backoff_ms`.
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Fraction (0 to 1) of each delay that's random, so requests that failed together don't all
+    /// retry together
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 300,
+            attempts: 6,
+            backoff_ms: 20,
+            max_backoff_ms: 320,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.attempts == 0 {
+            anyhow::bail!("A request needs at least one attempt");
+        }
+        if self.timeout_ms == 0 {
+            anyhow::bail!("The request timeout can't be zero");
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            anyhow::bail!("Jitter must be between 0 and 1");
+        }
+        if self.max_backoff_ms < self.backoff_ms {
+            anyhow::bail!("The maximum backoff can't be less than the initial backoff");
+        }
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// How long to wait before the given retry, counting from 1
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let base = self
+            .backoff_ms
+            .saturating_mul(
+                1u64.checked_shl(retry.saturating_sub(1))
+                    .unwrap_or(u64::MAX),
+            )
+            .min(self.max_backoff_ms);
+        let random: f64 = rand::thread_rng().gen_range(0.0..=1.0);
+        Duration::from_millis(base - (base as f64 * self.jitter * random) as u64)
+    }
+}
+
+/// How many requests an RPC call made to the device, and how many attempts they took between
+/// them. More attempts than requests points to a flaky bus rather than a dead device.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct RequestStats {
+    pub requests: u32,
+    pub attempts: u32,
+}
+
+struct RequestScope {
+    retry: Option<RetryPolicy>,
+    requests: AtomicU32,
+    attempts: AtomicU32,
+}
+
+tokio::task_local! {
+    static REQUEST_SCOPE: Arc<RequestScope>;
+}
+
+/// Run an RPC call, using the given retry policy (if any) in place of the provider's for every
+/// request it makes, and counting the attempts they take.
+pub async fn with_request_scope<F: Future>(
+    retry: Option<RetryPolicy>,
+    f: F,
+) -> (F::Output, RequestStats) {
+    let scope = Arc::new(RequestScope {
+        retry,
+        requests: AtomicU32::new(0),
+        attempts: AtomicU32::new(0),
+    });
+    let output = REQUEST_SCOPE.scope(scope.clone(), f).await;
+    let stats = RequestStats {
+        requests: scope.requests.load(Ordering::Relaxed),
+        attempts: scope.attempts.load(Ordering::Relaxed),
+    };
+    (output, stats)
+}
+
+/// The retry policy the current RPC call asked for, if it asked for one
+pub(super) fn scoped_policy() -> Option<RetryPolicy> {
+    REQUEST_SCOPE
+        .try_with(|scope| scope.retry.clone())
+        .ok()
+        .flatten()
+}
+
+pub(super) fn record_request(attempts: u32) {
+    REQUEST_SCOPE
+        .try_with(|scope| {
+            scope.requests.fetch_add(1, Ordering::Relaxed);
+            scope.attempts.fetch_add(attempts, Ordering::Relaxed);
+        })
+        .ok(); // ok since requests made outside of an RPC call (e.g. bulk upgrades) aren't counted
+}
@@ -18,7 +18,7 @@ use log::{info, warn};
 use rust_embed::RustEmbed;
 use tokio::{
     net::TcpStream,
-    sync::{mpsc, Mutex},
+    sync::{broadcast, mpsc, Mutex},
 };
 use tokio_util::codec::Framed;
 
@@ -31,6 +31,7 @@ use crate::{
     codecs::tcp_can_bridge::GrappleTcpCanBridgeCodec,
     devices::{
         device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse},
+        events::DeviceEvent,
         provider::{DeviceProvider, ProviderInfo},
     },
     ssh::SSHSession,
@@ -226,9 +227,6 @@ impl RoboRioDaemon {
                 .running
                 .store(true, std::sync::atomic::Ordering::Relaxed);
             let r = Self::do_loop(framed, inner.clone()).await;
-            inner
-                .running
-                .store(false, std::sync::atomic::Ordering::Relaxed);
             if will_deploy {
                 tokio::time::timeout(
                     tokio::time::Duration::from_secs(10),
@@ -238,6 +236,11 @@ impl RoboRioDaemon {
                 .ok();
             }
             inner.device_manager.reset().await;
+            // Only mark as stopped once we've reverted, so callers waiting on a disconnect don't
+            // leave the RoboRIO without robot code.
+            inner
+                .running
+                .store(false, std::sync::atomic::Ordering::Relaxed);
             match r {
                 Ok(_) => info!("RoboRioDaemon runner stopped gracefully"),
                 Err(e) => warn!("RoboRioDaemon runner stopped with error: {}", e),
@@ -271,6 +274,10 @@ impl DeviceProvider for RoboRioDaemon {
         })
     }
 
+    fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.inner.device_manager.subscribe()
+    }
+
     async fn device_manager_call(
         &self,
         req: DeviceManagerRequest,
@@ -0,0 +1,725 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
+
+use bounded_static::ToBoundedStatic;
+use grapple_frc_msgs::{
+    binmarshal::{AsymmetricCow, BitView, Demarshal, LengthTaggedPayloadOwned},
+    grapple::{
+        device_info::{GrappleDeviceInfo, GrappleModelId},
+        encapsulation::{BridgeMessages, EncapsulatedMesssage},
+        errors::GrappleError,
+        firmware::{FlashParameters, GrappleFirmwareMessage},
+        flexican::FlexiCANMessage,
+        fragments::{FragmentReassembler, FragmentReassemblerRx, FragmentReassemblerTx},
+        lasercan::{
+            LaserCanMeasurement, LaserCanMessage, LaserCanRangingMode, LaserCanRoi, LaserCanRoiU4,
+            LaserCanTimingBudget,
+        },
+        mitocandria::{
+            MitocandriaChannelRequest, MitocandriaChannelStatus, MitocandriaMessage,
+            MitocandriaStatusFrame,
+        },
+        GrappleBroadcastMessage, GrappleDeviceMessage, GrappleMessageId, Request,
+        TaggedGrappleMessage,
+    },
+    ManufacturerMessage, MessageId, Validate, DEVICE_ID_BROADCAST,
+};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+use crate::rpc::RpcBase;
+
+use super::{
+    device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse},
+    events::DeviceEvent,
+    provider::{DeviceProvider, ProviderInfo},
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum SimulatedDeviceState {
+    LaserCan {
+        mode: LaserCanRangingMode,
+        roi: LaserCanRoi,
+        budget: LaserCanTimingBudget,
+        distance_mm: u16,
+    },
+    Mitocandria {
+        channels: [MitocandriaChannelStatus; 5],
+    },
+    FlexiCan {
+        channels: Vec<SimulatedFlexiCanChannel>,
+    },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SimulatedFlexiCanChannel {
+    pub name: String,
+    pub bridging: bool,
+    /// Number of frames bridged so far, also used as the frame payload
+    pub frames_sent: u32,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SimulatedDevice {
+    pub serial: u32,
+    pub device_id: u8,
+    pub name: String,
+    pub version: String,
+    pub is_dfu: bool,
+    pub is_dfu_in_progress: bool,
+    /// Number of firmware bytes received during the current field upgrade
+    pub firmware_received: usize,
+    /// The ID and name last committed to EEPROM, which the device comes back with after a power
+    /// cycle
+    pub committed_id: u8,
+    pub committed_name: String,
+    pub state: SimulatedDeviceState,
+}
+
+impl SimulatedDevice {
+    pub fn new(model: GrappleModelId, serial: u32, device_id: u8) -> anyhow::Result<Self> {
+        let (version, state) = match model {
+            GrappleModelId::LaserCan => (
+                "2025.1.0",
+                SimulatedDeviceState::LaserCan {
+                    mode: LaserCanRangingMode::Short,
+                    roi: LaserCanRoi {
+                        x: LaserCanRoiU4(8),
+                        y: LaserCanRoiU4(8),
+                        w: LaserCanRoiU4(16),
+                        h: LaserCanRoiU4(16),
+                    },
+                    budget: LaserCanTimingBudget::TB33ms,
+                    distance_mm: 500,
+                },
+            ),
+            GrappleModelId::MitoCANdria => (
+                "2025.0.1",
+                SimulatedDeviceState::Mitocandria {
+                    channels: [
+                        MitocandriaChannelStatus::NonSwitchable { current: 500 },
+                        MitocandriaChannelStatus::NonSwitchable { current: 250 },
+                        MitocandriaChannelStatus::Switchable {
+                            enabled: true,
+                            current: 1200,
+                        },
+                        MitocandriaChannelStatus::Switchable {
+                            enabled: true,
+                            current: 800,
+                        },
+                        MitocandriaChannelStatus::Adjustable {
+                            enabled: true,
+                            voltage: 12000,
+                            voltage_setpoint: 12000,
+                            current: 300,
+                        },
+                    ],
+                },
+            ),
+            GrappleModelId::FlexiCAN => (
+                "2025.0.0",
+                SimulatedDeviceState::FlexiCan {
+                    channels: ["CAN0", "CAN1"]
+                        .into_iter()
+                        .map(|name| SimulatedFlexiCanChannel {
+                            name: name.to_owned(),
+                            bridging: false,
+                            frames_sent: 0,
+                        })
+                        .collect(),
+                },
+            ),
+            m => anyhow::bail!("Can't simulate a {:?}", m),
+        };
+
+        let name = format!("Simulated {:?}", model);
+        Ok(Self {
+            serial,
+            device_id,
+            name: name.clone(),
+            version: version.to_owned(),
+            is_dfu: false,
+            is_dfu_in_progress: false,
+            firmware_received: 0,
+            committed_id: device_id,
+            committed_name: name,
+            state,
+        })
+    }
+
+    /// Lose everything that wasn't committed to EEPROM
+    pub fn power_cycle(&mut self) {
+        self.device_id = self.committed_id;
+        self.name = self.committed_name.clone();
+    }
+
+    pub fn model_id(&self) -> GrappleModelId {
+        match self.state {
+            SimulatedDeviceState::LaserCan { .. } => GrappleModelId::LaserCan,
+            SimulatedDeviceState::Mitocandria { .. } => GrappleModelId::MitoCANdria,
+            SimulatedDeviceState::FlexiCan { .. } => GrappleModelId::FlexiCAN,
+        }
+    }
+
+    fn reply(&self, msg: GrappleDeviceMessage<'static>) -> TaggedGrappleMessage<'static> {
+        TaggedGrappleMessage::new(self.device_id, msg)
+    }
+
+    fn ack<T: Validate>(req: &T) -> Result<(), GrappleError<'static>> {
+        req.validate().map_err(|e| e.to_static())
+    }
+
+    /// Handle a message as the device would, returning any replies it puts on the bus.
+    pub fn handle(
+        &mut self,
+        device_id: u8,
+        msg: GrappleDeviceMessage<'static>,
+    ) -> Vec<TaggedGrappleMessage<'static>> {
+        let mut replies = vec![];
+
+        match msg {
+            GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(dinfo)) => {
+                match dinfo {
+                    GrappleDeviceInfo::EnumerateRequest => replies.push(self.reply(
+                        GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(
+                            GrappleDeviceInfo::EnumerateResponse {
+                                model_id: self.model_id(),
+                                serial: self.serial,
+                                is_dfu: self.is_dfu,
+                                is_dfu_in_progress: self.is_dfu_in_progress,
+                                version: Cow::<str>::Owned(self.version.clone()).into(),
+                                name: Cow::<str>::Owned(self.name.clone()).into(),
+                            },
+                        )),
+                    )),
+                    GrappleDeviceInfo::SetName { serial, name } if serial == self.serial => {
+                        self.name = name.0.into_owned();
+                    }
+                    GrappleDeviceInfo::SetId { serial, new_id } if serial == self.serial => {
+                        self.device_id = new_id;
+                    }
+                    GrappleDeviceInfo::CommitConfig { serial } if serial == self.serial => {
+                        self.committed_id = self.device_id;
+                        self.committed_name = self.name.clone();
+                    }
+                    _ => (),
+                }
+            }
+            GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::StartFieldUpgrade {
+                serial,
+            }) if serial == self.serial => {
+                self.is_dfu = true;
+                self.is_dfu_in_progress = false;
+                self.firmware_received = 0;
+            }
+            _ if device_id != self.device_id && device_id != DEVICE_ID_BROADCAST => (),
+            GrappleDeviceMessage::FirmwareUpdate(fw) if self.is_dfu => match fw {
+                GrappleFirmwareMessage::GetFlashParameters(Request::Request(())) => {
+                    replies.push(self.reply(GrappleDeviceMessage::FirmwareUpdate(
+                        GrappleFirmwareMessage::GetFlashParameters(Request::Ack(Ok(
+                            FlashParameters {
+                                flash_compat_version: 2,
+                                align: 8,
+                                payload_len: 8,
+                            },
+                        ))),
+                    )))
+                }
+                GrappleFirmwareMessage::UpdatePartV2(Request::Request(part)) => {
+                    self.is_dfu_in_progress = true;
+                    self.firmware_received = part.offset as usize + part.payload.len();
+                    replies.push(self.reply(GrappleDeviceMessage::FirmwareUpdate(
+                        GrappleFirmwareMessage::UpdatePartV2(Request::Ack(Ok(()))),
+                    )))
+                }
+                GrappleFirmwareMessage::UpdatePart(part) => {
+                    self.is_dfu_in_progress = true;
+                    self.firmware_received += part.len();
+                    replies.push(self.reply(GrappleDeviceMessage::FirmwareUpdate(
+                        GrappleFirmwareMessage::UpdatePartAck,
+                    )))
+                }
+                GrappleFirmwareMessage::UpdateDone => {
+                    self.is_dfu = false;
+                    self.is_dfu_in_progress = false;
+                }
+                _ => (),
+            },
+            GrappleDeviceMessage::DistanceSensor(lc) if !self.is_dfu => {
+                if let SimulatedDeviceState::LaserCan {
+                    mode, roi, budget, ..
+                } = &mut self.state
+                {
+                    let ack = match &lc {
+                        LaserCanMessage::SetRange(Request::Request(new_mode)) => {
+                            *mode = new_mode.clone();
+                            Some(LaserCanMessage::SetRange(Request::Ack(Ok(()))))
+                        }
+                        LaserCanMessage::SetRoi(Request::Request(new_roi)) => {
+                            // The sensor can't range with a ROI smaller than 4x4, which isn't
+                            // checked before the request is sent.
+                            let result = Self::ack(new_roi).and_then(|()| {
+                                match *new_roi.w < 4 || *new_roi.h < 4 {
+                                    true => Err(GrappleError::ParameterOutOfBounds(
+                                        Cow::Borrowed("LaserCanRoi: smaller than 4x4").into(),
+                                    )),
+                                    false => Ok(()),
+                                }
+                            });
+                            if result.is_ok() {
+                                *roi = new_roi.clone();
+                            }
+                            Some(LaserCanMessage::SetRoi(Request::Ack(result)))
+                        }
+                        LaserCanMessage::SetTimingBudget(Request::Request(new_budget)) => {
+                            *budget = new_budget.clone();
+                            Some(LaserCanMessage::SetTimingBudget(Request::Ack(Ok(()))))
+                        }
+                        LaserCanMessage::SetLedThreshold(Request::Request(_)) => Some(
+                            LaserCanMessage::SetLedThreshold(Request::Ack(Self::ack(&lc))),
+                        ),
+                        _ => None,
+                    };
+
+                    if let Some(ack) = ack {
+                        replies.push(self.reply(GrappleDeviceMessage::DistanceSensor(ack)));
+                    }
+                }
+            }
+            GrappleDeviceMessage::PowerDistributionModule(MitocandriaMessage::ChannelRequest(
+                req,
+            )) if !self.is_dfu => {
+                if let SimulatedDeviceState::Mitocandria { channels } = &mut self.state {
+                    let ack = match req {
+                        MitocandriaChannelRequest::SetSwitchableChannel(Request::Request(r)) => {
+                            let result = match channels.get_mut(r.channel as usize) {
+                                Some(MitocandriaChannelStatus::Switchable { enabled, .. })
+                                | Some(MitocandriaChannelStatus::Adjustable { enabled, .. }) => {
+                                    *enabled = r.enabled;
+                                    Ok(())
+                                }
+                                _ => Err(GrappleError::ParameterOutOfBounds(
+                                    Cow::Borrowed("Channel is not switchable").into(),
+                                )),
+                            };
+                            Some(MitocandriaChannelRequest::SetSwitchableChannel(
+                                Request::Ack(result),
+                            ))
+                        }
+                        MitocandriaChannelRequest::SetAdjustableChannel(Request::Request(r)) => {
+                            let result = match channels.get_mut(r.channel as usize) {
+                                Some(MitocandriaChannelStatus::Adjustable {
+                                    voltage,
+                                    voltage_setpoint,
+                                    ..
+                                }) => {
+                                    *voltage = r.voltage;
+                                    *voltage_setpoint = r.voltage;
+                                    Ok(())
+                                }
+                                _ => Err(GrappleError::ParameterOutOfBounds(
+                                    Cow::Borrowed("Channel is not adjustable").into(),
+                                )),
+                            };
+                            Some(MitocandriaChannelRequest::SetAdjustableChannel(
+                                Request::Ack(result),
+                            ))
+                        }
+                        MitocandriaChannelRequest::CalibrateAdjChannel(Request::Request(_)) => {
+                            Some(MitocandriaChannelRequest::CalibrateAdjChannel(
+                                Request::Ack(Ok(())),
+                            ))
+                        }
+                        MitocandriaChannelRequest::StartAutoCalibrate(Request::Request(())) => {
+                            Some(MitocandriaChannelRequest::StartAutoCalibrate(Request::Ack(
+                                Ok(()),
+                            )))
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(ack) = ack {
+                        replies.push(self.reply(GrappleDeviceMessage::PowerDistributionModule(
+                            MitocandriaMessage::ChannelRequest(ack),
+                        )));
+                    }
+                }
+            }
+            GrappleDeviceMessage::IOBreakout(FlexiCANMessage::Bridge(req)) if !self.is_dfu => {
+                if let SimulatedDeviceState::FlexiCan { channels } = &mut self.state {
+                    let out_of_bounds = || {
+                        GrappleError::ParameterOutOfBounds(Cow::Borrowed("No such channel").into())
+                    };
+                    let ack = match req {
+                        BridgeMessages::GetChannelName(Request::Request(channel)) => {
+                            Some(BridgeMessages::GetChannelName(Request::Ack(match channels
+                                .get(channel as usize)
+                            {
+                                Some(c) => Ok(Cow::<str>::Owned(c.name.clone()).into()),
+                                None => Err(out_of_bounds()),
+                            })))
+                        }
+                        BridgeMessages::StartBridge(Request::Request(channel)) => {
+                            Some(BridgeMessages::StartBridge(Request::Ack(
+                                match channels.get_mut(channel as usize) {
+                                    Some(c) => {
+                                        c.bridging = true;
+                                        Ok(())
+                                    }
+                                    None => Err(out_of_bounds()),
+                                },
+                            )))
+                        }
+                        BridgeMessages::StopBridge(Request::Request(channel)) => {
+                            Some(BridgeMessages::StopBridge(Request::Ack(
+                                match channels.get_mut(channel as usize) {
+                                    Some(c) => {
+                                        c.bridging = false;
+                                        Ok(())
+                                    }
+                                    None => Err(out_of_bounds()),
+                                },
+                            )))
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(ack) = ack {
+                        replies.push(self.reply(GrappleDeviceMessage::IOBreakout(
+                            FlexiCANMessage::Bridge(ack),
+                        )));
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        replies
+    }
+
+    /// The periodic status frame for this device, if it sends one.
+    pub fn status_frame(&mut self, t: f64) -> Option<TaggedGrappleMessage<'static>> {
+        if self.is_dfu {
+            return None;
+        }
+
+        match &mut self.state {
+            SimulatedDeviceState::LaserCan {
+                mode,
+                roi,
+                budget,
+                distance_mm,
+            } => {
+                // Wander a little so there's something to look at
+                let noise = (t * 7.0).sin() * 5.0 + (t * 0.5).sin() * 50.0;
+                Some(TaggedGrappleMessage::new(
+                    self.device_id,
+                    GrappleDeviceMessage::DistanceSensor(LaserCanMessage::Measurement(
+                        LaserCanMeasurement {
+                            status: 0,
+                            distance_mm: (*distance_mm as f64 + noise).max(0.0) as u16,
+                            ambient: 10,
+                            mode: mode.clone(),
+                            budget: budget.clone(),
+                            roi: roi.clone(),
+                        },
+                    )),
+                ))
+            }
+            SimulatedDeviceState::Mitocandria { channels } => Some(TaggedGrappleMessage::new(
+                self.device_id,
+                GrappleDeviceMessage::PowerDistributionModule(MitocandriaMessage::StatusFrame(
+                    MitocandriaStatusFrame {
+                        channels: channels.clone(),
+                    },
+                )),
+            )),
+            SimulatedDeviceState::FlexiCan { channels } => {
+                // Take turns between the channels that are bridging
+                let bridging: Vec<usize> = (0..channels.len())
+                    .filter(|&i| channels[i].bridging)
+                    .collect();
+                if bridging.is_empty() {
+                    return None;
+                }
+                let idx = bridging[(t * 10.0) as usize % bridging.len()];
+                let channel = &mut channels[idx];
+                channel.frames_sent += 1;
+
+                Some(TaggedGrappleMessage::new(
+                    self.device_id,
+                    GrappleDeviceMessage::IOBreakout(FlexiCANMessage::Bridge(
+                        BridgeMessages::BridgeMessage(EncapsulatedMesssage {
+                            channel: idx as u8,
+                            timestamp: (t * 1000.0) as u32,
+                            id: MessageId::from(0x0204_0000 | idx as u32),
+                            data: AsymmetricCow(Cow::Owned(LengthTaggedPayloadOwned::new(
+                                channel.frames_sent.to_le_bytes().to_vec(),
+                            ))),
+                        }),
+                    )),
+                ))
+            }
+        }
+    }
+}
+
+/// Put a message on the simulated bus, fragmenting and reassembling it as real hardware would.
+fn transmit(
+    tx: &mut FragmentReassemblerTx,
+    rx: &mut FragmentReassemblerRx,
+    now: i64,
+    msg: TaggedGrappleMessage<'static>,
+) -> anyhow::Result<Vec<(GrappleMessageId, GrappleDeviceMessage<'static>)>> {
+    let mut frames = vec![];
+    tx.maybe_fragment(msg.device_id, msg.msg, &mut |id, buf| {
+        frames.push((id, buf.to_vec()))
+    })
+    .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+
+    let mut out = vec![];
+    for (id, data) in frames {
+        let manufacturer_msg = ManufacturerMessage::read(&mut BitView::new(&data[..]), id)
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        if let ManufacturerMessage::Grapple(grpl_msg) = manufacturer_msg {
+            let mut storage = Vec::new();
+            if let Some((gid, grpl_unfragmented)) = rx
+                .defragment(now, &id, grpl_msg, &mut storage)
+                .map_err(|e| anyhow::anyhow!("{:?}", e))?
+            {
+                out.push((gid, grpl_unfragmented.to_static()));
+            }
+        }
+    }
+    Ok(out)
+}
+
+pub struct SimulatorInner {
+    address: String,
+    running: AtomicBool,
+    device_manager: DeviceManager,
+    devices: Mutex<Vec<SimulatedDevice>>,
+
+    stop_signal_tx: mpsc::Sender<()>,
+    stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+    send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+}
+
+/// A loopback provider with simulated LaserCAN, MitoCANdria and FlexiCAN devices on the other end.
+pub struct Simulator {
+    inner: Arc<SimulatorInner>,
+}
+
+impl Simulator {
+    pub fn new(address: String) -> Self {
+        let (send_tx, send_rx) = mpsc::channel(100);
+        let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+
+        let mut sends = HashMap::new();
+        sends.insert("CAN".to_owned(), send_tx);
+
+        let devices = vec![
+            SimulatedDevice::new(GrappleModelId::LaserCan, 0x5100_0001, 1).unwrap(),
+            SimulatedDevice::new(GrappleModelId::MitoCANdria, 0x5100_0002, 1).unwrap(),
+            SimulatedDevice::new(GrappleModelId::FlexiCAN, 0x5100_0003, 1).unwrap(),
+        ];
+
+        Self {
+            inner: Arc::new(SimulatorInner {
+                address,
+                running: AtomicBool::new(false),
+                device_manager: DeviceManager::new(sends),
+                devices: Mutex::new(devices),
+                stop_signal_tx,
+                stop_signal_rx: Mutex::new(stop_signal_rx),
+                send_rx: Mutex::new(send_rx),
+            }),
+        }
+    }
+
+    async fn do_loop(inner: Arc<SimulatorInner>) -> anyhow::Result<()> {
+        let mut send_rx = inner
+            .send_rx
+            .try_lock()
+            .map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+        let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+        let (mut host_rx, mut host_tx) = FragmentReassembler::new(1000, 8).split();
+        let (mut sim_rx, mut sim_tx) = FragmentReassembler::new(1000, 8).split();
+        let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+        let mut status_interval = tokio::time::interval(Duration::from_millis(100));
+        let epoch = Instant::now();
+
+        loop {
+            let now = epoch.elapsed().as_millis() as i64;
+
+            let replies = tokio::select! {
+              msg = send_rx.recv() => match msg {
+                Some(msg) => {
+                  let mut replies = vec![];
+                  for (gid, msg) in transmit(&mut host_tx, &mut sim_rx, now, msg)? {
+                    for device in inner.devices.lock().await.iter_mut() {
+                      replies.extend(device.handle(gid.device_id, msg.clone()));
+                    }
+                  }
+                  replies
+                },
+                None => vec![]
+              },
+              _ = status_interval.tick() => {
+                let t = epoch.elapsed().as_secs_f64();
+                inner.devices.lock().await.iter_mut().filter_map(|d| d.status_frame(t)).collect()
+              },
+              sig = stop_signal_rx.recv() => match sig {
+                Some(()) => {
+                  break;
+                },
+                None => vec![]
+              },
+              _ = device_manager_interval.tick() => {
+                inner.device_manager.on_tick().await?;
+                vec![]
+              }
+            };
+
+            for reply in replies {
+                for (gid, msg) in transmit(&mut sim_tx, &mut host_rx, now, reply)? {
+                    let device_id = gid.device_id;
+                    inner
+                        .device_manager
+                        .on_message(
+                            "CAN".to_owned(),
+                            gid,
+                            TaggedGrappleMessage::new(device_id, msg),
+                        )
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn do_start(inner: Arc<SimulatorInner>) -> anyhow::Result<()> {
+        info!("Starting simulator...");
+
+        tokio::task::spawn(async move {
+            inner
+                .running
+                .store(true, std::sync::atomic::Ordering::Relaxed);
+            let r = Self::do_loop(inner.clone()).await;
+            inner.device_manager.reset().await;
+            inner
+                .running
+                .store(false, std::sync::atomic::Ordering::Relaxed);
+            match r {
+                Ok(_) => info!("Simulator runner stopped gracefully"),
+                Err(e) => warn!("Simulator runner stopped with error: {}", e),
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for Simulator {
+    async fn connect(&self) -> anyhow::Result<()> {
+        Self::do_start(self.inner.clone()).await
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        self.inner.stop_signal_tx.send(()).await.ok();
+        Ok(())
+    }
+
+    async fn info(&self) -> anyhow::Result<ProviderInfo> {
+        Ok(ProviderInfo {
+            ty: "Simulator".to_owned(),
+            description: "Simulated Devices".to_owned(),
+            address: self.inner.address.clone(),
+            connected: self
+                .inner
+                .running
+                .load(std::sync::atomic::Ordering::Relaxed),
+        })
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.inner.device_manager.subscribe()
+    }
+
+    async fn device_manager_call(
+        &self,
+        req: DeviceManagerRequest,
+    ) -> anyhow::Result<DeviceManagerResponse> {
+        self.inner.device_manager.rpc_process(req).await
+    }
+
+    async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        self.rpc_call(req).await
+    }
+}
+
+#[rpc]
+impl Simulator {
+    async fn devices(&self) -> anyhow::Result<Vec<SimulatedDevice>> {
+        Ok(self.inner.devices.lock().await.clone())
+    }
+
+    async fn add_device(
+        &self,
+        model: GrappleModelId,
+        serial: u32,
+        device_id: u8,
+    ) -> anyhow::Result<()> {
+        let mut devices = self.inner.devices.lock().await;
+        if devices.iter().any(|d| d.serial == serial) {
+            anyhow::bail!("A simulated device with serial {} already exists", serial);
+        }
+        devices.push(SimulatedDevice::new(model, serial, device_id)?);
+        Ok(())
+    }
+
+    async fn remove_device(&self, serial: u32) -> anyhow::Result<()> {
+        self.inner
+            .devices
+            .lock()
+            .await
+            .retain(|d| d.serial != serial);
+        Ok(())
+    }
+
+    async fn set_device(&self, device: SimulatedDevice) -> anyhow::Result<()> {
+        let mut devices = self.inner.devices.lock().await;
+        let existing = devices
+            .iter_mut()
+            .find(|d| d.serial == device.serial)
+            .ok_or(anyhow::anyhow!(
+                "No simulated device with serial {}",
+                device.serial
+            ))?;
+        *existing = device;
+        Ok(())
+    }
+
+    async fn power_cycle(&self, serial: u32) -> anyhow::Result<()> {
+        self.inner
+            .devices
+            .lock()
+            .await
+            .iter_mut()
+            .find(|d| d.serial == serial)
+            .ok_or(anyhow::anyhow!(
+                "No simulated device with serial {}",
+                serial
+            ))?
+            .power_cycle();
+        Ok(())
+    }
+}
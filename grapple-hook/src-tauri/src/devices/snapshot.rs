@@ -0,0 +1,74 @@
+use grapple_frc_msgs::grapple::lasercan::{LaserCanRangingMode, LaserCanRoi, LaserCanTimingBudget};
+use serde::{Deserialize, Serialize};
+
+use super::DeviceType;
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct MitocandriaChannelConfig {
+    pub channel: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Voltage setpoint in mV, for adjustable channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voltage: Option<u16>,
+}
+
+/// Device-specific settings, beyond the ID and name common to all Grapple devices.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "type")]
+pub enum DeviceConfig {
+    LaserCan {
+        mode: LaserCanRangingMode,
+        roi: LaserCanRoi,
+        budget: LaserCanTimingBudget,
+    },
+    Mitocandria {
+        channels: Vec<MitocandriaChannelConfig>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DeviceSnapshot {
+    pub serial: u32,
+    pub device_type: DeviceType,
+    pub device_id: Option<u8>,
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<DeviceConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ConfigSnapshot {
+    pub devices: Vec<DeviceSnapshot>,
+}
+
+impl ConfigSnapshot {
+    pub fn load(path: &str, format: &ConfigFormat) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(match format {
+            ConfigFormat::Json => serde_json::from_str(&content)?,
+            ConfigFormat::Toml => toml::from_str(&content)?,
+        })
+    }
+
+    pub fn save(&self, path: &str, format: &ConfigFormat) -> anyhow::Result<()> {
+        let content = match format {
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+        };
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ConfigApplyResult {
+    pub serial: u32,
+    pub error: Option<String>,
+}
@@ -0,0 +1,274 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
+
+use ::socketcan::{tokio::CanSocket, CanFrame, EmbeddedFrame, ExtendedId, Frame};
+use bounded_static::ToBoundedStatic;
+use futures_util::StreamExt;
+use grapple_frc_msgs::{
+    binmarshal::{BitView, Demarshal, LengthTaggedPayload, LengthTaggedPayloadOwned},
+    bridge::BridgedCANMessage,
+    grapple::{fragments::FragmentReassembler, TaggedGrappleMessage},
+    ManufacturerMessage, MessageId,
+};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+use crate::{
+    canlog::{CanLog, CanLogRequest, CanLogResponse},
+    rpc::RpcBase,
+};
+
+use super::{
+    device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse},
+    events::DeviceEvent,
+    provider::{DeviceProvider, ProviderInfo},
+};
+
+const ARPHRD_CAN: &'static str = "280";
+/// How long to wait after failing to read before trying again, so a downed interface isn't spun on
+const READ_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+pub struct SocketCanInner {
+    interface: String,
+    running: AtomicBool,
+    device_manager: DeviceManager,
+
+    stop_signal_tx: mpsc::Sender<()>,
+    stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+    can_send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+    can_send_raw_rx: Mutex<mpsc::Receiver<(MessageId, Vec<u8>)>>,
+
+    canlog: CanLog,
+}
+
+/// A Linux SocketCAN interface, such as a CANable adapter or vcan0.
+pub struct SocketCan {
+    inner: Arc<SocketCanInner>,
+}
+
+impl SocketCan {
+    pub fn new(interface: String) -> Self {
+        let (can_send_tx, can_send_rx) = mpsc::channel(100);
+        let (can_send_raw_tx, can_send_raw_rx) = mpsc::channel(100);
+        let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+
+        let mut sends = HashMap::new();
+        sends.insert("CAN".to_owned(), can_send_tx);
+
+        Self {
+            inner: Arc::new(SocketCanInner {
+                interface,
+                running: AtomicBool::new(false),
+                device_manager: DeviceManager::new(sends),
+                stop_signal_tx,
+                stop_signal_rx: Mutex::new(stop_signal_rx),
+                can_send_rx: Mutex::new(can_send_rx),
+                can_send_raw_rx: Mutex::new(can_send_raw_rx),
+                canlog: CanLog::new(512, can_send_raw_tx),
+            }),
+        }
+    }
+
+    /// List the CAN interfaces available on this machine.
+    pub fn interfaces() -> Vec<String> {
+        let mut ifaces = vec![];
+        if let Ok(entries) = std::fs::read_dir("/sys/class/net") {
+            for entry in entries.flatten() {
+                let ty = std::fs::read_to_string(entry.path().join("type")).unwrap_or_default();
+                if ty.trim() == ARPHRD_CAN {
+                    ifaces.push(entry.file_name().to_string_lossy().into_owned());
+                }
+            }
+        }
+        ifaces
+    }
+
+    fn to_frame(msg: &BridgedCANMessage<'_>) -> anyhow::Result<CanFrame> {
+        let id = ExtendedId::new(msg.id.into()).ok_or(anyhow::anyhow!("Invalid CAN ID"))?;
+        CanFrame::new(id, &msg.data.0[..]).ok_or(anyhow::anyhow!(
+            "CAN frame too long ({} bytes)",
+            msg.data.len()
+        ))
+    }
+
+    /// Send a message, logging rather than failing if it can't be, since errors like a full TX
+    /// queue (ENOBUFS) or the bus being off are usually transient.
+    async fn write(socket: &CanSocket, interface: &str, msg: &BridgedCANMessage<'_>) {
+        let result = match Self::to_frame(msg) {
+            Ok(frame) => socket.write_frame(frame).await.map_err(Into::into),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("Could not send to {}: {}", interface, e);
+        }
+    }
+
+    async fn do_loop(mut socket: CanSocket, inner: Arc<SocketCanInner>) -> anyhow::Result<()> {
+        let mut can_send_rx = inner
+            .can_send_rx
+            .try_lock()
+            .map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+        let mut can_send_raw_rx = inner
+            .can_send_raw_rx
+            .try_lock()
+            .map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+        let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+        let (mut reassemble_rx, mut reassemble_tx) = FragmentReassembler::new(1000, 8).split();
+        let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+        let epoch = Instant::now();
+
+        loop {
+            tokio::select! {
+              frame = socket.next() => match frame {
+                Some(Ok(CanFrame::Data(frame))) if frame.is_extended() => {
+                  let msg = BridgedCANMessage {
+                    id: frame.raw_id().into(),
+                    timestamp: epoch.elapsed().as_millis() as u32,
+                    data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(frame.data().to_vec())).into()
+                  };
+
+                  let mut already_logged = false;
+                  let manufacturer_msg = ManufacturerMessage::read(&mut BitView::new(&msg.data.0[..]), msg.id);
+                  match manufacturer_msg {
+                    Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
+                      let mut storage = Vec::new();
+                      if let Ok(Some((gid, grpl_unfragmented))) = reassemble_rx.defragment(msg.timestamp as i64, &msg.id, grpl_msg, &mut storage) {
+                        inner.canlog.on_message(&msg, Some(&grpl_unfragmented)).await;
+                        already_logged = true;
+
+                        inner.device_manager.on_message("CAN".to_owned(), gid, TaggedGrappleMessage::new(msg.id.device_id, grpl_unfragmented.to_static())).await?;
+                      }
+                    },
+                    _ => ()
+                  }
+
+                  if !already_logged {
+                    inner.canlog.on_message(&msg, None).await;
+                  }
+                },
+                // Standard IDs, remote and error frames aren't part of the FRC protocol
+                Some(Ok(_)) => (),
+                Some(Err(e)) => {
+                  warn!("Could not read from {}: {}", inner.interface, e);
+                  tokio::time::sleep(READ_ERROR_BACKOFF).await;
+                },
+                None => anyhow::bail!("CAN interface closed"),
+              },
+              msg = can_send_rx.recv() => match msg {
+                Some(msg) => {
+                  let TaggedGrappleMessage { device_id, msg } = msg;
+
+                  let mut msgs = vec![];
+                  reassemble_tx.maybe_fragment(device_id, msg.clone(), &mut |id, buf| {
+                    msgs.push(BridgedCANMessage { id, timestamp: 0, data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(buf.to_vec())).into() });
+                  }).ok();
+
+                  let len = msgs.len();
+                  for (i, cur_msg) in msgs.into_iter().enumerate() {
+                    inner.canlog.on_message(&cur_msg, (i == len - 1).then(|| &msg)).await;
+
+                    Self::write(&socket, &inner.interface, &cur_msg).await;
+                  }
+                },
+                None => ()
+              },
+              msg = can_send_raw_rx.recv() => match msg {
+                  Some((id, data)) => {
+                    let msg = BridgedCANMessage { id, timestamp: 0, data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(data)).into() };
+                    inner.canlog.on_message(&msg, None).await;
+                    Self::write(&socket, &inner.interface, &msg).await;
+                  },
+                  None => ()
+              },
+              sig = stop_signal_rx.recv() => match sig {
+                Some(()) => {
+                  break;
+                },
+                None => ()
+              },
+              _ = device_manager_interval.tick() => {
+                inner.device_manager.on_tick().await?;
+              }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn do_start(inner: Arc<SocketCanInner>) -> anyhow::Result<()> {
+        info!("Connecting to {}...", inner.interface);
+
+        let socket = CanSocket::open(&inner.interface)?;
+
+        info!("Connected!");
+
+        tokio::task::spawn(async move {
+            inner
+                .running
+                .store(true, std::sync::atomic::Ordering::Relaxed);
+            let r = Self::do_loop(socket, inner.clone()).await;
+            inner.device_manager.reset().await;
+            inner
+                .running
+                .store(false, std::sync::atomic::Ordering::Relaxed);
+            match r {
+                Ok(_) => info!("SocketCAN runner stopped gracefully"),
+                Err(e) => warn!("SocketCAN runner stopped with error: {}", e),
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for SocketCan {
+    async fn connect(&self) -> anyhow::Result<()> {
+        Self::do_start(self.inner.clone()).await
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        self.inner.stop_signal_tx.send(()).await.ok();
+        Ok(())
+    }
+
+    async fn info(&self) -> anyhow::Result<ProviderInfo> {
+        Ok(ProviderInfo {
+            ty: "SocketCAN".to_owned(),
+            description: format!("SocketCAN ({})", self.inner.interface),
+            address: self.inner.interface.clone(),
+            connected: self
+                .inner
+                .running
+                .load(std::sync::atomic::Ordering::Relaxed),
+        })
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<DeviceEvent> {
+        self.inner.device_manager.subscribe()
+    }
+
+    async fn device_manager_call(
+        &self,
+        req: DeviceManagerRequest,
+    ) -> anyhow::Result<DeviceManagerResponse> {
+        self.inner.device_manager.rpc_process(req).await
+    }
+
+    async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        self.rpc_call(req).await
+    }
+}
+
+#[rpc]
+impl SocketCan {
+    async fn canlog_call(&self, req: CanLogRequest) -> anyhow::Result<CanLogResponse> {
+        self.inner.canlog.rpc_process(req).await
+    }
+}
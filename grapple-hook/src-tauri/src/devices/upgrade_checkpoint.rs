@@ -0,0 +1,70 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+/// How far an interrupted V2 field upgrade got, so it can pick up where it left off.
+#[derive(Clone)]
+pub struct UpgradeCheckpoint {
+    pub firmware: Arc<Vec<u8>>,
+    pub payload_len: u16,
+    /// Offset of the first chunk the device has not acknowledged
+    pub next_offset: u32,
+}
+
+#[derive(Default)]
+struct Checkpoints {
+    checkpoints: HashMap<u32, UpgradeCheckpoint>,
+    active: HashSet<u32>,
+}
+
+// Kept for the life of the process rather than in the DeviceManager, since USB providers are
+// recreated when the device is unplugged and replugged.
+fn checkpoints() -> &'static Mutex<Checkpoints> {
+    static CHECKPOINTS: OnceLock<Mutex<Checkpoints>> = OnceLock::new();
+    CHECKPOINTS.get_or_init(|| Mutex::new(Checkpoints::default()))
+}
+
+pub fn get(serial: u32) -> Option<UpgradeCheckpoint> {
+    checkpoints()
+        .lock()
+        .unwrap()
+        .checkpoints
+        .get(&serial)
+        .cloned()
+}
+
+pub fn set(serial: u32, checkpoint: UpgradeCheckpoint) {
+    checkpoints()
+        .lock()
+        .unwrap()
+        .checkpoints
+        .insert(serial, checkpoint);
+}
+
+pub fn clear(serial: u32) {
+    checkpoints().lock().unwrap().checkpoints.remove(&serial);
+}
+
+pub fn is_active(serial: u32) -> bool {
+    checkpoints().lock().unwrap().active.contains(&serial)
+}
+
+/// Marks an upgrade as running on the device until the guard is dropped, so the same device
+/// isn't flashed by two workers at once.
+pub struct ActiveUpgrade(u32);
+
+impl ActiveUpgrade {
+    pub fn acquire(serial: u32) -> anyhow::Result<Self> {
+        if !checkpoints().lock().unwrap().active.insert(serial) {
+            anyhow::bail!("A firmware upgrade is already running on this device");
+        }
+        Ok(Self(serial))
+    }
+}
+
+impl Drop for ActiveUpgrade {
+    fn drop(&mut self) {
+        checkpoints().lock().unwrap().active.remove(&self.0);
+    }
+}
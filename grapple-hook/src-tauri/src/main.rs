@@ -9,11 +9,15 @@ use std::{
 // use devices::device_manager::DeviceManager;
 use env_logger::Builder;
 use grapple_hook::{
-    devices::provider_manager::ProviderManager,
+    devices::{
+        events::{DeviceEvent, ProviderDeviceEvent},
+        provider_manager::ProviderManager,
+    },
     rpc::RpcBase,
     updates::{most_recent_update_available, LightReleaseResponse},
 };
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+use tokio::sync::broadcast;
 
 static NEW_UPDATE: Mutex<Option<LightReleaseResponse>> = Mutex::new(None);
 
@@ -38,10 +42,7 @@ async fn main() {
     let provider_manager = Arc::new(ProviderManager::new().await);
     let most_recent = tokio::time::timeout(
         Duration::from_secs(2),
-        most_recent_update_available(
-            "https://api.github.com/repos/GrappleRobotics/GrappleHook/releases",
-            |_| true,
-        ),
+        most_recent_update_available("GrappleRobotics/GrappleHook", |_| true),
     )
     .await;
 
@@ -56,13 +57,35 @@ async fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .manage(provider_manager.clone())
-        .setup(|app| {
+        .setup(move |app| {
             log::info!(
                 "This version: {}, Most Recent: {:?}",
                 app.package_info().version.to_string(),
                 most_recent.clone().map(|x| x.tag_name)
             );
 
+            // Forward device events to the frontend, so it doesn't have to poll for them
+            let mut events = provider_manager.subscribe();
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    match events.recv().await {
+                        // Every device sends several of these a second, and the UI polls each
+                        // device's status itself, so they're left to other subscribers like the
+                        // CLI rather than flooding the webview.
+                        Ok(ProviderDeviceEvent {
+                            event: DeviceEvent::StatusFrame { .. },
+                            ..
+                        }) => (),
+                        Ok(event) => {
+                            handle.emit("device_event", event).ok();
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => (),
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
             if let Some(most_recent) = most_recent {
                 if let Ok(vers) = semver::Version::parse(&most_recent.tag_name[1..]) {
                     if vers > app.package_info().version {
@@ -1,34 +1,179 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
+
 use chrono::{DateTime, Utc};
-use reqwest::header::USER_AGENT;
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+use log::warn;
+use reqwest::{header::USER_AGENT, Url};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+const DEFAULT_RELEASE_ENDPOINT: &str = "https://api.github.com/repos";
+// Release feeds have to come back quicker than the 2s the startup update check waits, so a
+// hanging network falls back to the cache instead of taking the whole check down with it.
+const FETCH_TIMEOUT: Duration = Duration::from_millis(1500);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+static UPDATE_SOURCE: RwLock<Option<UpdateSourceConfig>> = RwLock::new(None);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum UpdateChannel {
+    Stable,
+    /// Stable releases, as well as those marked as prereleases
+    Prerelease,
+}
+
+/// Where update checks and firmware downloads are fetched from, shared by everything that looks
+/// for releases.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateSourceConfig {
+    /// The base of the GitHub-style release API. Can be pointed at a local mirror for events
+    /// without internet access.
+    pub endpoint: String,
+    pub channel: UpdateChannel,
+    /// Proxy for all update requests, e.g. `http://proxy.local:3128`
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
+
+impl Default for UpdateSourceConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: DEFAULT_RELEASE_ENDPOINT.to_owned(),
+            channel: UpdateChannel::Stable,
+            proxy: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
 #[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LightReleaseResponse {
     pub name: String,
     pub tag_name: String,
     pub published_at: String,
     pub html_url: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
 }
 
-pub async fn most_recent_update_available<F: Fn(&LightReleaseResponse) -> bool>(
-    repo: &str,
-    acceptance_filter: F,
-) -> anyhow::Result<Option<LightReleaseResponse>> {
-    let client = reqwest::Client::new();
+fn update_source_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("GrappleHook").join("update_source.toml"))
+}
+
+// The endpoint can also be set with the GRAPPLE_HOOK_RELEASE_ENDPOINT environment variable, which
+// takes priority over the saved config.
+fn load_update_source() -> UpdateSourceConfig {
+    let mut config: UpdateSourceConfig = update_source_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default();
+    if let Ok(endpoint) = std::env::var("GRAPPLE_HOOK_RELEASE_ENDPOINT") {
+        config.endpoint = endpoint;
+    }
+    config
+}
+
+pub fn update_source() -> UpdateSourceConfig {
+    if let Some(config) = UPDATE_SOURCE.read().unwrap().as_ref() {
+        return config.clone();
+    }
+    UPDATE_SOURCE
+        .write()
+        .unwrap()
+        .get_or_insert_with(load_update_source)
+        .clone()
+}
+
+/// Change where updates come from, saving it for next time
+pub fn set_update_source(config: UpdateSourceConfig) -> anyhow::Result<()> {
+    if let Some(proxy) = &config.proxy {
+        reqwest::Proxy::all(proxy)?;
+    }
+
+    if let Some(path) = update_source_path() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(&config)?)?;
+    }
+
+    *UPDATE_SOURCE.write().unwrap() = Some(config);
+    Ok(())
+}
+
+/// The release feed for a repository, e.g. `GrappleRobotics/LaserCAN`
+pub fn releases_url(repo: &str) -> String {
+    format!(
+        "{}/{}/releases",
+        update_source().endpoint.trim_end_matches('/'),
+        repo
+    )
+}
+
+fn http_client() -> anyhow::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = &update_source().proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
 
-    let response: Vec<LightReleaseResponse> = client
-        .get(repo)
+async fn fetch_releases(repo: &str) -> anyhow::Result<Vec<LightReleaseResponse>> {
+    Ok(http_client()?
+        .get(releases_url(repo))
         .header(USER_AGENT, "GrappleHook")
+        .timeout(FETCH_TIMEOUT)
         .send()
         .await?
+        .error_for_status()?
         .json()
-        .await?;
+        .await?)
+}
+
+/// Find the most recent release of a repository (e.g. `GrappleRobotics/LaserCAN`) accepted by the
+/// filter. Releases in the firmware cache are consulted first and merged with those fetched from
+/// the release endpoint, so checks keep working (and imported bundles are seen) while offline.
+pub async fn most_recent_update_available<F: Fn(&LightReleaseResponse) -> bool>(
+    repo: &str,
+    acceptance_filter: F,
+) -> anyhow::Result<Option<LightReleaseResponse>> {
+    let cached = read_cached_releases(repo).await;
+
+    let releases = match fetch_releases(repo).await {
+        Ok(online) => {
+            let merged = merge_releases(cached.unwrap_or_default(), online);
+            if let Err(e) = write_cached_releases(repo, &merged).await {
+                warn!("Could not cache releases of {}: {}", repo, e);
+            }
+            merged
+        }
+        Err(e) => match cached {
+            Some(cached) => {
+                warn!("Could not fetch releases of {}, using cache: {}", repo, e);
+                cached
+            }
+            None => return Err(e),
+        },
+    };
 
+    let include_prereleases = update_source().channel == UpdateChannel::Prerelease;
     let mut most_recent: Option<(LightReleaseResponse, DateTime<Utc>)> = None;
 
-    for r in response {
-        if acceptance_filter(&r) {
+    for r in releases {
+        if (include_prereleases || !r.prerelease) && acceptance_filter(&r) {
             let dt = DateTime::parse_from_rfc3339(&r.published_at).ok();
 
             if let Some(dt) = dt {
@@ -41,3 +186,179 @@ pub async fn most_recent_update_available<F: Fn(&LightReleaseResponse) -> bool>(
 
     Ok(most_recent.map(|x| x.0))
 }
+
+/// Pick the firmware for the given model out of a release's assets. Bundles are preferred over
+/// raw binaries, and assets named for the model over those that aren't.
+pub fn choose_firmware_asset<'a>(
+    release: &'a LightReleaseResponse,
+    model: &GrappleModelId,
+) -> Option<&'a ReleaseAsset> {
+    let model_name = format!("{:?}", model).to_lowercase();
+
+    release
+        .assets
+        .iter()
+        .filter_map(|asset| {
+            let name = asset.name.to_lowercase();
+            let kind = if name.ends_with(".grplbndl") || name.ends_with(".zip") {
+                2
+            } else if name.ends_with(".bin") {
+                1
+            } else {
+                return None;
+            };
+            Some(((name.contains(&model_name), kind), asset))
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, asset)| asset)
+}
+
+pub fn firmware_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or(std::env::temp_dir())
+        .join("GrappleHook")
+        .join("firmware")
+}
+
+fn sanitise_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_owned()
+}
+
+fn asset_cache_path(release: &LightReleaseResponse, asset_name: &str) -> PathBuf {
+    firmware_cache_dir()
+        .join(sanitise_file_name(&release.tag_name))
+        .join(sanitise_file_name(asset_name))
+}
+
+fn releases_cache_path(repo: &str) -> PathBuf {
+    firmware_cache_dir()
+        .join("releases")
+        .join(format!("{}.json", sanitise_file_name(repo)))
+}
+
+async fn read_cached_releases(repo: &str) -> Option<Vec<LightReleaseResponse>> {
+    let content = tokio::fs::read(releases_cache_path(repo)).await.ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+async fn write_cached_releases(
+    repo: &str,
+    releases: &[LightReleaseResponse],
+) -> std::io::Result<()> {
+    write_cache(
+        &releases_cache_path(repo),
+        &serde_json::to_vec_pretty(releases)?,
+    )
+    .await
+}
+
+/// Releases from `newer` replace those of the same tag in `older`, keeping any assets that were
+/// only in the cache (e.g. imported bundles).
+fn merge_releases(
+    older: Vec<LightReleaseResponse>,
+    newer: Vec<LightReleaseResponse>,
+) -> Vec<LightReleaseResponse> {
+    let mut merged = newer;
+    for old in older {
+        match merged.iter_mut().find(|r| r.tag_name == old.tag_name) {
+            Some(release) => {
+                for asset in old.assets {
+                    if !release.assets.iter().any(|a| a.name == asset.name) {
+                        release.assets.push(asset);
+                    }
+                }
+            }
+            None => merged.push(old),
+        }
+    }
+    merged
+}
+
+/// Add a firmware file to the cache as an asset of the given release, so it's found by update
+/// checks and downloads without needing to reach the release endpoint.
+pub async fn import_release_asset(
+    repo: &str,
+    release: LightReleaseResponse,
+    asset_name: &str,
+    data: &[u8],
+) -> anyhow::Result<()> {
+    let path = asset_cache_path(&release, asset_name);
+    write_cache(&path, data).await?;
+
+    // There's nowhere to download an imported asset from, so point at the copy in the cache
+    let asset = ReleaseAsset {
+        name: asset_name.to_owned(),
+        browser_download_url: Url::from_file_path(&path)
+            .map_err(|_| anyhow::anyhow!("Invalid cache path: {}", path.display()))?
+            .to_string(),
+        size: data.len() as u64,
+    };
+
+    let mut releases = read_cached_releases(repo).await.unwrap_or_default();
+    match releases.iter_mut().find(|r| r.tag_name == release.tag_name) {
+        Some(existing) => {
+            existing.assets.retain(|a| a.name != asset.name);
+            existing.assets.push(asset);
+        }
+        None => releases.push(LightReleaseResponse {
+            assets: vec![asset],
+            ..release
+        }),
+    }
+    write_cached_releases(repo, &releases).await?;
+    Ok(())
+}
+
+/// Download a release asset, reusing the copy in the firmware cache if it's already been fetched.
+pub async fn download_asset(
+    release: &LightReleaseResponse,
+    asset: &ReleaseAsset,
+) -> anyhow::Result<Vec<u8>> {
+    let path = asset_cache_path(release, &asset.name);
+
+    if let Ok(data) = tokio::fs::read(&path).await {
+        if asset.size == 0 || data.len() as u64 == asset.size {
+            return Ok(data);
+        }
+    }
+
+    let url = Url::parse(&asset.browser_download_url)?;
+    if url.scheme() == "file" {
+        let path = url
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("Invalid file URL: {}", url))?;
+        return Ok(tokio::fs::read(&path).await?);
+    }
+
+    let data = http_client()?
+        .get(url)
+        .header(USER_AGENT, "GrappleHook")
+        .timeout(DOWNLOAD_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec();
+
+    // Failing to cache isn't fatal, we just have to download it again next time
+    if let Err(e) = write_cache(&path, &data).await {
+        warn!("Could not cache firmware at {}: {}", path.display(), e);
+    }
+
+    Ok(data)
+}
+
+async fn write_cache(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, data).await
+}
@@ -0,0 +1,122 @@
+use std::{
+    borrow::Cow,
+    time::{Duration, SystemTime},
+};
+
+use grapple_frc_msgs::{
+    binmarshal::{LengthTaggedPayload, LengthTaggedPayloadOwned},
+    bridge::BridgedCANMessage,
+};
+use grapple_hook::{
+    canlog::{
+        read_recording, CanLog, CanLogRecorder, CanLogRequest, CanLogResponse, MailboxItem,
+        RecordingFormat,
+    },
+    rpc::RpcBase,
+};
+use tokio::sync::mpsc;
+
+/// (offset from the first message, ID, data), covering an empty frame, a classic frame and one
+/// that only fits in CAN FD
+fn messages() -> Vec<(Duration, u32, Vec<u8>)> {
+    vec![
+        (Duration::ZERO, 0x0206_0401, vec![]),
+        (
+            Duration::from_millis(10),
+            0x1234_5678,
+            vec![1, 2, 3, 4, 5, 6, 7, 8],
+        ),
+        (
+            Duration::from_millis(250),
+            0x1fff_ffff,
+            (0..20).collect::<Vec<u8>>(),
+        ),
+    ]
+}
+
+fn temp_path() -> String {
+    let path = std::env::temp_dir().join(format!("grapple-hook-test-{}", uuid::Uuid::new_v4()));
+    path.to_str().unwrap().to_owned()
+}
+
+fn message(id: u32, timestamp: u32, data: Vec<u8>) -> BridgedCANMessage<'static> {
+    BridgedCANMessage {
+        id: id.into(),
+        timestamp,
+        data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(data)).into(),
+    }
+}
+
+fn round_trip(format: RecordingFormat) {
+    let path = temp_path();
+    let start = SystemTime::now();
+
+    let mut recorder = CanLogRecorder::create(path.clone(), format.clone()).unwrap();
+    for (seq, (offset, id, data)) in messages().into_iter().enumerate() {
+        let item = MailboxItem {
+            seq,
+            raw: message(id, 1000 + offset.as_millis() as u32, data),
+            grpl_defrag: None,
+        };
+        recorder.write(start + offset, &item).unwrap();
+    }
+    assert_eq!(recorder.finish().unwrap().messages, messages().len());
+
+    let recorded = read_recording(&path, &format).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let recorded = recorded
+        .into_iter()
+        .map(|(offset, msg)| {
+            let id: u32 = msg.id.into();
+            (offset, id, msg.data.0[..].to_vec())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(recorded, messages());
+}
+
+#[test]
+fn candump_round_trips() {
+    round_trip(RecordingFormat::Candump);
+}
+
+#[test]
+fn pcap_round_trips() {
+    round_trip(RecordingFormat::Pcap);
+}
+
+#[test]
+fn json_round_trips() {
+    round_trip(RecordingFormat::Json);
+}
+
+#[tokio::test]
+async fn recordings_are_written_off_the_message_loop() {
+    let (tx, _rx) = mpsc::channel(1);
+    let log = CanLog::new(100, tx);
+    let path = temp_path();
+
+    log.rpc_process(CanLogRequest::start_recording {
+        path: path.clone(),
+        format: RecordingFormat::Candump,
+    })
+    .await
+    .unwrap();
+    for (_, id, data) in messages() {
+        log.on_message(&message(id, 0, data), None).await;
+    }
+    let status = match log
+        .rpc_process(CanLogRequest::stop_recording {})
+        .await
+        .unwrap()
+    {
+        CanLogResponse::stop_recording(status) => status.unwrap(),
+        _ => panic!("Unexpected response from CAN log"),
+    };
+    assert_eq!(status.messages, messages().len());
+
+    // Everything queued is on disk once the recording's stopped
+    let recorded = read_recording(&path, &RecordingFormat::Candump).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(recorded.len(), messages().len());
+}
@@ -0,0 +1,376 @@
+use std::{collections::HashMap, time::Duration};
+
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+
+use grapple_hook::{
+    devices::{
+        device_manager::{AgeOffConfig, DeviceId, DeviceManagerRequest, DeviceManagerResponse},
+        provider::DeviceProvider,
+        retry::RetryPolicy,
+        simulator::{SimulatedDevice, Simulator, SimulatorRequest, SimulatorResponse},
+        snapshot::ConfigFormat,
+        DeviceInfo,
+    },
+    rpc::RpcBase,
+};
+
+const LASERCAN: u32 = 0x5100_0001;
+const MITOCANDRIA: u32 = 0x5100_0002;
+const FLEXICAN: u32 = 0x5100_0003;
+
+/// The info and device class of each enumerated device
+async fn enumerated(simulator: &Simulator) -> HashMap<DeviceId, (DeviceInfo, String)> {
+    match simulator
+        .device_manager_call(DeviceManagerRequest::devices {})
+        .await
+        .unwrap()
+    {
+        DeviceManagerResponse::devices(devices) => devices
+            .into_values()
+            .flatten()
+            .map(|(id, info, class, ..)| (id, (info, class)))
+            .collect(),
+        _ => panic!("Unexpected response from device manager"),
+    }
+}
+
+/// Wait for a device to enumerate
+async fn wait_for(simulator: &Simulator, id: DeviceId) {
+    for _ in 0..50 {
+        if enumerated(simulator).await.contains_key(&id) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{:?} never enumerated", id);
+}
+
+/// Start a simulator and wait for the device with the given serial to enumerate
+async fn simulator(serial: u32) -> Simulator {
+    let simulator = Simulator::new("simulator".to_owned());
+    simulator.connect().await.unwrap();
+    wait_for(&simulator, DeviceId::Serial(serial)).await;
+    simulator
+}
+
+/// The simulator's side of a device, as opposed to what's been reported to the device manager
+async fn simulated(simulator: &Simulator, serial: u32) -> SimulatedDevice {
+    match simulator
+        .rpc_process(SimulatorRequest::devices {})
+        .await
+        .unwrap()
+    {
+        SimulatorResponse::devices(devices) => {
+            devices.into_iter().find(|d| d.serial == serial).unwrap()
+        }
+        _ => panic!("Unexpected response from simulator"),
+    }
+}
+
+async fn call_id(
+    simulator: &Simulator,
+    device_id: DeviceId,
+    data: serde_json::Value,
+    retry: Option<RetryPolicy>,
+) -> anyhow::Result<serde_json::Value> {
+    match simulator
+        .device_manager_call(DeviceManagerRequest::call {
+            domain: "CAN".to_owned(),
+            device_id,
+            data,
+            retry,
+        })
+        .await?
+    {
+        DeviceManagerResponse::call(rsp) => Ok(rsp),
+        _ => anyhow::bail!("Unexpected response from device manager"),
+    }
+}
+
+async fn call(
+    simulator: &Simulator,
+    serial: u32,
+    data: serde_json::Value,
+    retry: Option<RetryPolicy>,
+) -> anyhow::Result<serde_json::Value> {
+    call_id(simulator, DeviceId::Serial(serial), data, retry).await
+}
+
+#[tokio::test]
+async fn enumerates_simulated_devices() {
+    let simulator = simulator(LASERCAN).await;
+    wait_for(&simulator, DeviceId::Serial(MITOCANDRIA)).await;
+    wait_for(&simulator, DeviceId::Serial(FLEXICAN)).await;
+
+    let devices = enumerated(&simulator).await;
+    assert_eq!(devices[&DeviceId::Serial(LASERCAN)].1, "LaserCAN");
+    assert_eq!(devices[&DeviceId::Serial(MITOCANDRIA)].1, "MitoCANdria");
+    assert_eq!(devices[&DeviceId::Serial(FLEXICAN)].1, "FlexiCAN");
+
+    simulator.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn lasercan_configuration_is_acked() {
+    let simulator = simulator(LASERCAN).await;
+
+    call(
+        &simulator,
+        LASERCAN,
+        serde_json::json!({ "method": "set_range", "data": { "mode": "Long" } }),
+        None,
+    )
+    .await
+    .unwrap();
+    call(
+        &simulator,
+        LASERCAN,
+        serde_json::json!({ "method": "set_roi", "data": { "roi": { "x": 6, "y": 10, "w": 4, "h": 8 } } }),
+        None,
+    )
+    .await
+    .unwrap();
+    call(
+        &simulator,
+        LASERCAN,
+        serde_json::json!({ "method": "set_timing_budget", "data": { "budget": "TB50ms" } }),
+        None,
+    )
+    .await
+    .unwrap();
+
+    let state = serde_json::to_value(simulated(&simulator, LASERCAN).await.state).unwrap();
+    assert_eq!(state["LaserCan"]["mode"], "Long");
+    assert_eq!(
+        state["LaserCan"]["roi"],
+        serde_json::json!({ "x": 6, "y": 10, "w": 4, "h": 8 })
+    );
+    assert_eq!(state["LaserCan"]["budget"], "TB50ms");
+
+    simulator.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn imported_ids_survive_a_power_cycle() {
+    let simulator = simulator(LASERCAN).await;
+
+    let mut snapshot = match simulator
+        .device_manager_call(DeviceManagerRequest::snapshot_config {})
+        .await
+        .unwrap()
+    {
+        DeviceManagerResponse::snapshot_config(snapshot) => snapshot,
+        _ => panic!("Unexpected response from device manager"),
+    };
+    snapshot.devices.retain(|d| d.serial == LASERCAN);
+    snapshot.devices[0].device_id = Some(5);
+    snapshot.devices[0].name = Some("Intake".to_owned());
+
+    let path =
+        std::env::temp_dir().join(format!("grapple-hook-test-{}.json", uuid::Uuid::new_v4()));
+    let path = path.to_str().unwrap().to_owned();
+    snapshot.save(&path, &ConfigFormat::Json).unwrap();
+
+    let results = match simulator
+        .device_manager_call(DeviceManagerRequest::import_config {
+            path: path.clone(),
+            format: ConfigFormat::Json,
+            commit: true,
+        })
+        .await
+        .unwrap()
+    {
+        DeviceManagerResponse::import_config(results) => results,
+        _ => panic!("Unexpected response from device manager"),
+    };
+    std::fs::remove_file(&path).ok();
+    assert!(results.iter().all(|r| r.error.is_none()), "{:?}", results);
+
+    // Once the new ID is seen, the commit sent after it has been handled too
+    for _ in 0..50 {
+        let devices = enumerated(&simulator).await;
+        if devices[&DeviceId::Serial(LASERCAN)].0.device_id == Some(5) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    simulator
+        .rpc_process(SimulatorRequest::power_cycle { serial: LASERCAN })
+        .await
+        .unwrap();
+
+    let device = simulated(&simulator, LASERCAN).await;
+    assert_eq!(device.device_id, 5);
+    assert_eq!(device.name, "Intake");
+
+    simulator.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn mitocandria_history_window_is_bounded() {
+    let simulator = simulator(MITOCANDRIA).await;
+    let set_window = |window_s: u64| {
+        call(
+            &simulator,
+            MITOCANDRIA,
+            serde_json::json!({ "method": "set_history_window", "data": { "window_s": window_s } }),
+            None,
+        )
+    };
+
+    set_window(60).await.unwrap();
+    // Longer than the history can hold, rather than quietly keeping less than asked for
+    assert!(set_window(24 * 60 * 60).await.is_err());
+    assert!(set_window(0).await.is_err());
+
+    simulator.disconnect().await.unwrap();
+}
+
+/// Put the LaserCAN into DFU and start flashing it with firmware of the given length, which is just
+/// enough for validation to recognise it as LaserCAN firmware
+async fn start_lasercan_upgrade(simulator: &Simulator, len: usize) -> Vec<u8> {
+    call(
+        simulator,
+        LASERCAN,
+        serde_json::json!({ "method": "start_field_upgrade", "data": {} }),
+        None,
+    )
+    .await
+    .unwrap();
+    wait_for(simulator, DeviceId::Dfu(LASERCAN)).await;
+
+    let mut firmware = vec![0u8; len];
+    firmware[0x150..0x154].copy_from_slice(&[0xBE, 0xBA, 0xFE, 0xCA]);
+    firmware[0x15c] = GrappleModelId::LaserCan as u8;
+
+    call_id(
+        simulator,
+        DeviceId::Dfu(LASERCAN),
+        serde_json::json!({ "method": "do_field_upgrade", "data": { "data": firmware } }),
+        None,
+    )
+    .await
+    .unwrap();
+    firmware
+}
+
+/// Wait for the LaserCAN to leave DFU, returning how much firmware it received
+async fn wait_for_upgrade(simulator: &Simulator) -> usize {
+    for _ in 0..100 {
+        let device = simulated(simulator, LASERCAN).await;
+        if !device.is_dfu {
+            return device.firmware_received;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Device never left DFU");
+}
+
+#[tokio::test]
+async fn field_upgrade_flashes_the_whole_image() {
+    let simulator = simulator(LASERCAN).await;
+
+    let firmware = start_lasercan_upgrade(&simulator, 1000).await;
+    assert_eq!(wait_for_upgrade(&simulator).await, firmware.len());
+
+    simulator.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn interrupted_upgrade_resumes_when_the_device_is_back() {
+    let simulator = simulator(LASERCAN).await;
+    simulator
+        .device_manager_call(DeviceManagerRequest::set_age_off {
+            config: AgeOffConfig {
+                offline_after_ms: 500,
+                forget_after_ms: None,
+            },
+        })
+        .await
+        .unwrap();
+
+    let firmware = start_lasercan_upgrade(&simulator, 40_000).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Unplug it part way through, until the upgrade has given up on it
+    let device = simulated(&simulator, LASERCAN).await;
+    assert!(device.is_dfu_in_progress);
+    simulator
+        .rpc_process(SimulatorRequest::remove_device { serial: LASERCAN })
+        .await
+        .unwrap();
+    for _ in 0..100 {
+        let result = call_id(
+            &simulator,
+            DeviceId::Dfu(LASERCAN),
+            serde_json::json!({ "method": "upgrade_result", "data": {} }),
+            None,
+        )
+        .await
+        .unwrap();
+        if result["data"]["result"] == "Failed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    // It comes back to the same entry, still waiting for the rest of its firmware
+    simulator
+        .rpc_process(SimulatorRequest::add_device {
+            model: GrappleModelId::LaserCan,
+            serial: LASERCAN,
+            device_id: device.device_id,
+        })
+        .await
+        .unwrap();
+    simulator
+        .rpc_process(SimulatorRequest::set_device { device })
+        .await
+        .unwrap();
+
+    assert_eq!(wait_for_upgrade(&simulator).await, firmware.len());
+
+    simulator.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_requests_get_their_own_acks() {
+    let simulator = simulator(LASERCAN).await;
+
+    // Both are valid as far as the host can tell and are answered with the same reply ID, but the
+    // device rejects the second (it's too small to range with), so each caller has to get the ack
+    // for its own request. No retries, so a request whose ack went to the other caller fails
+    // rather than getting another go.
+    let retry = Some(RetryPolicy {
+        attempts: 1,
+        ..Default::default()
+    });
+    let (good, bad) = tokio::join!(
+        call(
+            &simulator,
+            LASERCAN,
+            serde_json::json!({ "method": "set_roi", "data": { "roi": { "x": 8, "y": 8, "w": 4, "h": 4 } } }),
+            retry.clone(),
+        ),
+        call(
+            &simulator,
+            LASERCAN,
+            serde_json::json!({ "method": "set_roi", "data": { "roi": { "x": 8, "y": 8, "w": 2, "h": 2 } } }),
+            retry.clone(),
+        ),
+    );
+
+    assert!(good.is_ok(), "{:?}", good);
+    assert!(
+        bad.as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("smaller than 4x4"),
+        "{:?}",
+        bad
+    );
+
+    simulator.disconnect().await.unwrap();
+}
@@ -0,0 +1,117 @@
+//! Runs against a virtual CAN interface, which can be set up with
+//! `ip link add dev vcan0 type vcan && ip link set up vcan0`. Skipped if there isn't one.
+#![cfg(target_os = "linux")]
+
+use std::time::Duration;
+
+use grapple_hook::{
+    canlog::{CanLogRequest, CanLogResponse},
+    devices::{
+        provider::DeviceProvider,
+        socketcan::{SocketCan, SocketCanRequest, SocketCanResponse},
+    },
+    rpc::RpcBase,
+};
+use socketcan::{tokio::CanSocket, CanFrame, EmbeddedFrame, ExtendedId, Frame};
+
+const INTERFACE: &str = "vcan0";
+
+fn vcan_available() -> bool {
+    let available = SocketCan::interfaces().iter().any(|i| i == INTERFACE);
+    if !available {
+        eprintln!("{} isn't available, skipping", INTERFACE);
+    }
+    available
+}
+
+async fn canlog(provider: &SocketCan, req: CanLogRequest) -> CanLogResponse {
+    match provider
+        .rpc_process(SocketCanRequest::canlog_call { req })
+        .await
+        .unwrap()
+    {
+        SocketCanResponse::canlog_call(rsp) => rsp,
+        _ => panic!("Unexpected response from provider"),
+    }
+}
+
+async fn send_raw(provider: &SocketCan, id: u32, data: Vec<u8>) {
+    canlog(
+        provider,
+        CanLogRequest::send_raw {
+            id: id.into(),
+            data,
+        },
+    )
+    .await;
+}
+
+/// Wait for a frame with the given ID to turn up on the bus
+async fn receive(bus: &CanSocket, id: u32) -> CanFrame {
+    tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            let frame = bus.read_frame().await.unwrap();
+            if frame.raw_id() == id {
+                return frame;
+            }
+        }
+    })
+    .await
+    .expect("Frame never turned up on the bus")
+}
+
+#[tokio::test]
+async fn frames_are_sent_and_received() {
+    if !vcan_available() {
+        return;
+    }
+    let bus = CanSocket::open(INTERFACE).unwrap();
+    let provider = SocketCan::new(INTERFACE.to_owned());
+    provider.connect().await.unwrap();
+    canlog(&provider, CanLogRequest::set_log_enabled { enabled: true }).await;
+
+    send_raw(&provider, 0x1234_5678, vec![1, 2, 3]).await;
+    assert_eq!(receive(&bus, 0x1234_5678).await.data(), &[1, 2, 3]);
+
+    let frame = CanFrame::new(ExtendedId::new(0x0abc_def0).unwrap(), &[4, 5]).unwrap();
+    bus.write_frame(frame).await.unwrap();
+
+    let mut received = false;
+    for _ in 0..10 {
+        let CanLogResponse::read_after(items) =
+            canlog(&provider, CanLogRequest::read_after { seq: 0 }).await
+        else {
+            panic!("Unexpected response from CAN log");
+        };
+        received = items.iter().any(|item| {
+            let id: u32 = item.raw.id.into();
+            id == 0x0abc_def0 && item.raw.data.0[..] == [4, 5]
+        });
+        if received {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(received, "Frame from the bus never made it to the CAN log");
+
+    provider.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn unsendable_frames_dont_stop_the_provider() {
+    if !vcan_available() {
+        return;
+    }
+    let bus = CanSocket::open(INTERFACE).unwrap();
+    let provider = SocketCan::new(INTERFACE.to_owned());
+    provider.connect().await.unwrap();
+
+    // Too long for a classic frame, so it can't be sent
+    send_raw(&provider, 0x1234_0001, vec![0; 12]).await;
+    send_raw(&provider, 0x1234_0002, vec![6]).await;
+
+    assert_eq!(receive(&bus, 0x1234_0002).await.data(), &[6]);
+    assert!(provider.info().await.unwrap().connected);
+
+    provider.disconnect().await.unwrap();
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+use grapple_hook::updates::{
+    choose_firmware_asset, download_asset, import_release_asset, most_recent_update_available,
+    LightReleaseResponse,
+};
+
+const FIRMWARE: &[u8] = b"not really firmware";
+
+/// A release endpoint on localhost, counting the requests made for each path
+struct Stub {
+    address: String,
+    hits: Mutex<HashMap<String, usize>>,
+}
+
+impl Stub {
+    fn hits(&self, path: &str) -> usize {
+        self.hits.lock().unwrap().get(path).copied().unwrap_or(0)
+    }
+
+    fn serve(&self, mut stream: TcpStream) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).unwrap() == 0 || header == "\r\n" {
+                break;
+            }
+        }
+
+        let path = request_line.split(' ').nth(1).unwrap_or("/");
+        let (status, body) = self.respond(path);
+        // The client may have given up by now
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            body.len()
+        )
+        .and_then(|()| stream.write_all(&body))
+        .ok();
+    }
+
+    fn respond(&self, path: &str) -> (&'static str, Vec<u8>) {
+        let hits = {
+            let mut hits = self.hits.lock().unwrap();
+            let count = hits.entry(path.to_owned()).or_default();
+            *count += 1;
+            *count
+        };
+
+        match path {
+            "/Grapple/Stable/releases" => ("200 OK", releases(&self.address).into_bytes()),
+            // Goes away after the first request, to test falling back to the cache
+            "/Grapple/Flaky/releases" if hits == 1 => {
+                ("200 OK", releases(&self.address).into_bytes())
+            }
+            "/Grapple/Flaky/releases" => ("500 Internal Server Error", vec![]),
+            "/Grapple/Hanging/releases" if hits == 1 => {
+                ("200 OK", releases(&self.address).into_bytes())
+            }
+            "/Grapple/Hanging/releases" => {
+                std::thread::sleep(Duration::from_secs(10));
+                ("200 OK", releases(&self.address).into_bytes())
+            }
+            "/files/lasercan.bin" => ("200 OK", FIRMWARE.to_vec()),
+            _ => ("404 Not Found", vec![]),
+        }
+    }
+}
+
+fn releases(address: &str) -> String {
+    format!(
+        r#"[
+            {{
+                "name": "v1.0.0", "tag_name": "v1.0.0", "published_at": "2025-01-01T00:00:00Z",
+                "html_url": "https://example.com/v1.0.0", "assets": []
+            }},
+            {{
+                "name": "v1.1.0", "tag_name": "v1.1.0", "published_at": "2025-02-01T00:00:00Z",
+                "html_url": "https://example.com/v1.1.0",
+                "assets": [
+                    {{ "name": "lasercan.bin", "browser_download_url": "{address}/files/lasercan.bin", "size": {} }}
+                ]
+            }},
+            {{
+                "name": "v1.2.0-beta", "tag_name": "v1.2.0-beta", "published_at": "2025-03-01T00:00:00Z",
+                "html_url": "https://example.com/v1.2.0-beta", "prerelease": true, "assets": []
+            }}
+        ]"#,
+        FIRMWARE.len()
+    )
+}
+
+/// Start the stub (once per test binary), and point the update source and the firmware cache at
+/// it and at a fresh directory respectively.
+fn stub() -> &'static Stub {
+    static STUB: OnceLock<&'static Stub> = OnceLock::new();
+    STUB.get_or_init(|| {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stub: &'static Stub = Box::leak(Box::new(Stub {
+            address: format!("http://{}", listener.local_addr().unwrap()),
+            hits: Mutex::new(HashMap::new()),
+        }));
+
+        let dir = std::env::temp_dir().join(format!("grapple-hook-test-{}", uuid::Uuid::new_v4()));
+        std::env::set_var("XDG_CACHE_HOME", dir.join("cache"));
+        std::env::set_var("XDG_CONFIG_HOME", dir.join("config"));
+        std::env::set_var("GRAPPLE_HOOK_RELEASE_ENDPOINT", &stub.address);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                // One thread per request, so a hanging response doesn't hold up the other tests
+                std::thread::spawn(move || stub.serve(stream));
+            }
+        });
+
+        stub
+    })
+}
+
+#[tokio::test]
+async fn finds_the_most_recent_stable_release() {
+    stub();
+    let release = most_recent_update_available("Grapple/Stable", |_| true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(release.tag_name, "v1.1.0");
+
+    let release = most_recent_update_available("Grapple/Stable", |r| r.assets.is_empty())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(release.tag_name, "v1.0.0");
+}
+
+#[tokio::test]
+async fn falls_back_to_cached_releases() {
+    let stub = stub();
+    let online = most_recent_update_available("Grapple/Flaky", |_| true)
+        .await
+        .unwrap()
+        .unwrap();
+    let offline = most_recent_update_available("Grapple/Flaky", |_| true)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(stub.hits("/Grapple/Flaky/releases"), 2);
+    assert_eq!(online.tag_name, offline.tag_name);
+}
+
+#[tokio::test]
+async fn hanging_endpoint_falls_back_to_cached_releases_in_time() {
+    stub();
+    most_recent_update_available("Grapple/Hanging", |_| true)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // The same deadline GrappleHook gives its own update check at startup
+    let offline = tokio::time::timeout(
+        Duration::from_secs(2),
+        most_recent_update_available("Grapple/Hanging", |_| true),
+    )
+    .await
+    .expect("Update check outlasted the startup deadline")
+    .unwrap()
+    .unwrap();
+    assert_eq!(offline.tag_name, "v1.1.0");
+}
+
+#[tokio::test]
+async fn missing_releases_are_an_error() {
+    stub();
+    assert!(most_recent_update_available("Grapple/Missing", |_| true)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn downloads_are_cached() {
+    let stub = stub();
+    let release = most_recent_update_available("Grapple/Stable", |_| true)
+        .await
+        .unwrap()
+        .unwrap();
+    let asset = choose_firmware_asset(&release, &GrappleModelId::LaserCan).unwrap();
+
+    assert_eq!(download_asset(&release, asset).await.unwrap(), FIRMWARE);
+    assert_eq!(download_asset(&release, asset).await.unwrap(), FIRMWARE);
+    assert_eq!(stub.hits("/files/lasercan.bin"), 1);
+}
+
+#[tokio::test]
+async fn imported_assets_are_read_from_the_cache() {
+    stub();
+    let release = LightReleaseResponse {
+        name: "v2.0.0".to_owned(),
+        tag_name: "v2.0.0".to_owned(),
+        published_at: "2025-04-01T00:00:00Z".to_owned(),
+        html_url: "https://example.com/v2.0.0".to_owned(),
+        prerelease: false,
+        assets: vec![],
+    };
+    import_release_asset("Grapple/Imported", release, "lasercan.bin", FIRMWARE)
+        .await
+        .unwrap();
+
+    // The endpoint doesn't know about this repo, so this can only come from the cache
+    let release = most_recent_update_available("Grapple/Imported", |_| true)
+        .await
+        .unwrap()
+        .unwrap();
+    let asset = choose_firmware_asset(&release, &GrappleModelId::LaserCan).unwrap();
+    assert!(asset.browser_download_url.starts_with("file://"));
+    assert_eq!(download_asset(&release, asset).await.unwrap(), FIRMWARE);
+}
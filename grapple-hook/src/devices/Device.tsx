@@ -2,7 +2,7 @@ import React, { useEffect, useState } from "react";
 import { Alert, Button, Col, FormControl, ProgressBar, Row } from "react-bootstrap";
 import "./Device.scss";
 import confirmBool, { confirmModal } from "../Confirm";
-import { DeviceId, DeviceInfo, DeviceType, FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, GrappleDeviceRequest, GrappleDeviceResponse, GrappleModelId, LightReleaseResponse } from "../schema";
+import { DeviceId, DeviceInfo, DeviceType, FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, FirmwareUpgradeError, FirmwareUpgradeResult, GrappleDeviceRequest, GrappleDeviceResponse, GrappleModelId, LightReleaseResponse } from "../schema";
 import Bug from "../Bug";
 import { rpc } from "../rpc";
 import { useToasts } from "../toasts";
@@ -10,6 +10,7 @@ import LaserCanComponent from "./LaserCan";
 import OldVersionDevice from "./OldVersionDevice";
 import FlexiCanComponent from "./FlexiCan";
 import MitocandriaComponent from "./Mitocandria";
+import UnknownGrappleDevice from "./UnknownGrappleDevice";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
 import { faInfoCircle, faMagicWandSparkles } from "@fortawesome/free-solid-svg-icons";
 
@@ -19,7 +20,8 @@ const FACTORIES: { [k: string]: FactoryFunc } = {
   "GrappleFirmwareUpgrade": (info, invoke) => <FirmwareUpdateComponent info={info} invoke={invoke} />,
   "LaserCAN": (info, invoke) => <LaserCanComponent info={info} invoke={invoke} />,
   "MitoCANdria": (info, invoke) => <MitocandriaComponent info={info} invoke={invoke} />,
-  "FlexiCAN": (info, invoke) => <FlexiCanComponent info={info} invoke={invoke} />
+  "FlexiCAN": (info, invoke) => <FlexiCanComponent info={info} invoke={invoke} />,
+  "UnknownGrappleDevice": (info, invoke) => <UnknownGrappleDevice info={info} invoke={invoke} />
 };
 const getFactory = (device_class: string) => FACTORIES[device_class]
 
@@ -152,6 +154,17 @@ export function GrappleDeviceHeaderComponent(props: GrappleDeviceHeaderComponent
   </React.Fragment>
 }
 
+function describeUpgradeError(error: FirmwareUpgradeError) {
+  switch (error.type) {
+    case "Timeout":
+      return `Timed out waiting for the device to acknowledge chunk ${error.data.chunk} (offset ${error.data.offset})`;
+    case "Nack":
+      return `Device rejected chunk ${error.data.chunk} (offset ${error.data.offset}): ${error.data.error}`;
+    case "Other":
+      return error.data.message;
+  }
+}
+
 type FirmwareUpdateComponentProps = {
   info: DeviceInfo,
   invoke: (msg: FirmwareUpgradeDeviceRequest) => Promise<FirmwareUpgradeDeviceResponse>
@@ -161,6 +174,8 @@ export function FirmwareUpdateComponent(props: FirmwareUpdateComponentProps) {
   const { invoke } = props;
 
   const [ progress, setProgress ] = useState<number | null>(null);
+  const [ result, setResult ] = useState<FirmwareUpgradeResult | null>(null);
+  const [ canResume, setCanResume ] = useState<boolean>(false);
   const [ firmwareURL, setFirmwareURL ] = useState<string | null>(null);
   const { addError } = useToasts();
   
@@ -169,6 +184,12 @@ export function FirmwareUpdateComponent(props: FirmwareUpdateComponentProps) {
       rpc<FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, "progress">(invoke, "progress", {})
         .then(setProgress)
         .catch(e => {});    // Discard, it's usually a message to say that the device is disconnected and the UI fragment just hasn't been evicted yet.
+      rpc<FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, "upgrade_result">(invoke, "upgrade_result", {})
+        .then(setResult)
+        .catch(e => {});
+      rpc<FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, "can_resume_field_upgrade">(invoke, "can_resume_field_upgrade", {})
+        .then(setCanResume)
+        .catch(e => {});
     }, 250);
 
     rpc<FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, "get_firmware_url">(invoke, "get_firmware_url", {})
@@ -187,6 +208,11 @@ export function FirmwareUpdateComponent(props: FirmwareUpdateComponentProps) {
     reader.readAsArrayBuffer(file);
   }
 
+  const resumeUpgrade = () => {
+    rpc<FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, "resume_field_upgrade">(invoke, "resume_field_upgrade", {})
+      .catch(addError);
+  }
+
   return <React.Fragment>
     {
       progress ? <React.Fragment>
@@ -194,6 +220,21 @@ export function FirmwareUpdateComponent(props: FirmwareUpdateComponentProps) {
           <Col> <ProgressBar min={0} max={100} now={progress} variant="purple" animated striped /> </Col>
         </Row>
       </React.Fragment> : <React.Fragment>
+        { result?.result === "Failed" && <Row className="mb-2">
+          <Col>
+            <Alert variant="danger" className="m-0">
+              Firmware upgrade failed: { describeUpgradeError(result.data.error) }
+            </Alert>
+          </Col>
+        </Row> }
+        { canResume && <Row className="mb-2">
+          <Col className="text-muted">
+            <FontAwesomeIcon icon={faInfoCircle} /> &nbsp; This device was interrupted part way through a firmware upgrade, which can be picked up where it left off.
+          </Col>
+          <Col md="auto">
+            <Button size="sm" variant="purple" onClick={resumeUpgrade}> Resume Upgrade </Button>
+          </Col>
+        </Row> }
         { firmwareURL && <Row className="mb-2">
           <Col className="text-muted">
             <FontAwesomeIcon icon={faInfoCircle} /> &nbsp; You can download new firmware for this device <a target="_blank" href={firmwareURL}>here.</a>
@@ -1,10 +1,12 @@
 import { useEffect, useState } from "react"
-import { DeviceInfo, FlexiCanRequest, FlexiCanResponse, FlexiCanStatus } from "../schema"
+import { DeviceInfo, FlexiCanChannelStatus, FlexiCanRequest, FlexiCanResponse, FlexiCanStatus, LightReleaseResponse } from "../schema"
 import { useToasts } from "../toasts"
 import { rpc } from "../rpc"
-import { Button, Col, ProgressBar, Row } from "react-bootstrap"
+import { Button, Col, Row, Table } from "react-bootstrap"
 import { GrappleDeviceHeaderComponent } from "./Device"
 
+export const CHANNELS = [0, 1]
+
 export type FlexiCanProps = {
   info: DeviceInfo,
   invoke: (msg: FlexiCanRequest) => Promise<FlexiCanResponse>
@@ -15,6 +17,7 @@ export default function FlexiCanComponent(props: FlexiCanProps) {
   const { addError } = useToasts();
 
   const [ status, setStatus ] = useState<FlexiCanStatus>();
+  const [ updateDetails, setUpdateDetails ] = useState<LightReleaseResponse | null>(null);
 
   useEffect(() => {
     const interval = setInterval(() => {
@@ -22,9 +25,34 @@ export default function FlexiCanComponent(props: FlexiCanProps) {
         .then(setStatus)
         .catch(e => {});  // Discard, it's usually a message to say that the device is disconnected and the UI fragment just hasn't been evicted yet.
     }, 50);
+
+    // Names are stored in the status once fetched, so we only need to ask for them once.
+    CHANNELS.forEach(channel => {
+      rpc<FlexiCanRequest, FlexiCanResponse, "get_channel_name">(invoke, "get_channel_name", { channel })
+        .catch(e => {})
+    });
+
+    rpc<FlexiCanRequest, FlexiCanResponse, "check_for_new_firmware">(invoke, "check_for_new_firmware", {})
+      .then(setUpdateDetails)
+      .catch(e => {})
+
     return () => clearInterval(interval);
   }, []);
 
+  const toggleBridge = (channel: FlexiCanChannelStatus) => {
+    if (channel.bridging) {
+      rpc<FlexiCanRequest, FlexiCanResponse, "stop_bridge">(invoke, "stop_bridge", { channel: channel.channel })
+        .catch(addError)
+    } else {
+      rpc<FlexiCanRequest, FlexiCanResponse, "start_bridge">(invoke, "start_bridge", { channel: channel.channel })
+        .catch(addError)
+    }
+  };
+
+  const channels: FlexiCanChannelStatus[] = CHANNELS.map(channel =>
+    status?.channels.find(c => c.channel === channel) || { channel, bridging: false, frames_bridged: 0 }
+  ).concat(status?.channels.filter(c => !CHANNELS.includes(c.channel)) || []);
+
   return <div className="powerful-panda">
     <Row className="mb-2">
       <Col>
@@ -32,12 +60,43 @@ export default function FlexiCanComponent(props: FlexiCanProps) {
           info={info}
           invoke={async (msg) => await rpc<FlexiCanRequest, FlexiCanResponse, "grapple">(invoke, "grapple", { msg })}
           start_dfu={async () => await rpc<FlexiCanRequest, FlexiCanResponse, "start_field_upgrade">(invoke, "start_field_upgrade", {})}
+          update_details={updateDetails}
         />
       </Col>
     </Row>
     <Row className="mb-2">
       <Col>
-        
+        <Table size="sm" className="m-0">
+          <thead>
+            <tr>
+              <th> Channel </th>
+              <th> Bridge </th>
+              <th> Frames Bridged </th>
+              <th> Last Frame </th>
+            </tr>
+          </thead>
+          <tbody>
+            {
+              channels.map(channel => <tr key={channel.channel}>
+                <td> { channel.name || `Channel ${channel.channel}` } </td>
+                <td>
+                  <Button size="sm" variant={channel.bridging ? "green" : "red"} onClick={() => toggleBridge(channel)}>
+                    { channel.bridging ? "BRIDGING" : "STOPPED" }
+                  </Button>
+                </td>
+                <td> { channel.frames_bridged } </td>
+                <td className="text-muted">
+                  {
+                    channel.last_frame && <span>
+                      0x{ channel.last_frame.id.device_type.toString(16) }/{ channel.last_frame.id.manufacturer }/{ channel.last_frame.id.api_class }.{ channel.last_frame.id.api_index }/{ channel.last_frame.id.device_id }
+                      &nbsp; [{ channel.last_frame.data.map(b => b.toString(16).padStart(2, "0")).join(" ") }]
+                    </span>
+                  }
+                </td>
+              </tr>)
+            }
+          </tbody>
+        </Table>
       </Col>
     </Row>
   </div>
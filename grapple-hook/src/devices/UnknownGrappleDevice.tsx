@@ -0,0 +1,35 @@
+import { Alert, Col, Row } from "react-bootstrap";
+import { DeviceInfo, UnknownGrappleDeviceRequest, UnknownGrappleDeviceResponse } from "../schema"
+import { rpc } from "../rpc";
+import { GrappleDeviceHeaderComponent, renderDeviceType } from "./Device";
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
+import { faCircleQuestion } from "@fortawesome/free-solid-svg-icons";
+
+export type UnknownGrappleDeviceComponentProps = {
+  info: DeviceInfo,
+  invoke: (msg: UnknownGrappleDeviceRequest) => Promise<UnknownGrappleDeviceResponse>
+}
+
+export default function UnknownGrappleDevice(props: UnknownGrappleDeviceComponentProps) {
+  const { info, invoke } = props;
+
+  return <div>
+    <Row className="mb-2">
+      <Col>
+        <GrappleDeviceHeaderComponent
+          info={info}
+          invoke={async (msg) => await rpc<UnknownGrappleDeviceRequest, UnknownGrappleDeviceResponse, "grapple">(invoke, "grapple", { msg })}
+          start_dfu={async () => await rpc<UnknownGrappleDeviceRequest, UnknownGrappleDeviceResponse, "start_field_upgrade">(invoke, "start_field_upgrade", {})}
+        />
+      </Col>
+    </Row>
+    <Row className="mb-2">
+      <Alert variant="warning">
+        <FontAwesomeIcon icon={faCircleQuestion} size="2x" /> &nbsp;
+        <span style={{ fontSize: '2em' }}> { renderDeviceType(info.device_type) } isn't supported by this version of GrappleHook </span>
+        <br />
+        <span> You can still blink, rename and change the ID of this device, or upgrade it with a firmware bundle. Update GrappleHook to configure it. </span>
+      </Alert>
+    </Row>
+  </div>
+}
@@ -6,10 +6,11 @@ import { FontAwesomeIcon } from "@fortawesome/react-fontawesome"
 import { faPlus } from "@fortawesome/free-solid-svg-icons"
 import { confirmModal } from "../Confirm"
 import BufferedFormControl from "../BufferedFormControl"
-import { DeviceId, DeviceInfo, DeviceManagerRequest, DeviceManagerResponse, ProviderInfo, ProviderManagerRequest, ProviderManagerResponse, WrappedDeviceProviderRequest, WrappedDeviceProviderResponse } from "../schema"
+import { DeviceId, DeviceInfo, DeviceManagerRequest, DeviceManagerResponse, DevicePresence, ProviderDeviceEvent, ProviderInfo, ProviderManagerRequest, ProviderManagerResponse, WrappedDeviceProviderRequest, WrappedDeviceProviderResponse } from "../schema"
 import { useToasts } from "../toasts"
 import { rpc } from "../rpc"
 import update from "immutability-helper";
+import { listen } from "@tauri-apps/api/event";
 
 type ProviderManagerProps = {
   invoke: (msg: ProviderManagerRequest) => Promise<ProviderManagerResponse>,
@@ -20,7 +21,7 @@ export default function ProviderManagerComponent(props: ProviderManagerProps) {
   const { addError } = useToasts();
 
   const [ providers, setProviders ] = useState<{ [key: string]: ProviderInfo }>({});
-  const [ devices, setDevices ] = useState<{ [key: string]: { [domain: string]: [DeviceId, DeviceInfo, string][] } }>({});
+  const [ devices, setDevices ] = useState<{ [key: string]: { [domain: string]: [DeviceId, DeviceInfo, string, number[], DevicePresence][] } }>({});
 
   const provider_rpc = (address: string) => {
     return async (msg: WrappedDeviceProviderRequest) => {
@@ -47,7 +48,7 @@ export default function ProviderManagerComponent(props: ProviderManagerProps) {
   }
 
   useEffect(() => {
-    const interval = setInterval(() => {
+    const refresh = () => {
       rpc<ProviderManagerRequest, ProviderManagerResponse, "providers">(invoke, "providers", {})
         .then((providers) => {
           setProviders(providers);
@@ -63,8 +64,15 @@ export default function ProviderManagerComponent(props: ProviderManagerProps) {
           });
         })
         .catch(addError)
-    }, 500);
-    return () => clearInterval(interval);
+    };
+
+    const interval = setInterval(refresh, 2000);
+    // Pick up devices coming and going straight away, rather than waiting for the next poll
+    const unlisten = listen<ProviderDeviceEvent>("device_event", refresh);
+    return () => {
+      clearInterval(interval);
+      unlisten.then(f => f());
+    };
   }, [])
 
   return <React.Fragment>
@@ -84,8 +92,8 @@ export default function ProviderManagerComponent(props: ProviderManagerProps) {
                     </Nav.Link>
                   </Nav.Item>,
                   ...Object.keys(devices[key] || {}).flatMap(domain => [
-                    devices[key][domain].map(([device_id, device_info, device_class]) => (
-                      <DevicePillComponent provider_key={key} domain={domain} device_id={device_id} device_info={device_info} device_class={device_class} />
+                    devices[key][domain].map(([device_id, device_info, device_class, id_conflicts, presence]) => (
+                      <DevicePillComponent provider_key={key} domain={domain} device_id={device_id} device_info={device_info} device_class={device_class} id_conflicts={id_conflicts} presence={presence} />
                     ))
                   ])
                 ]
@@ -119,8 +127,8 @@ export default function ProviderManagerComponent(props: ProviderManagerProps) {
   </React.Fragment>
 }
 
-export function DevicePillComponent(props: { provider_key: string, domain: string, device_id: DeviceId, device_info: DeviceInfo, device_class: string }) {
-  const { provider_key, domain, device_id, device_info } = props;
+export function DevicePillComponent(props: { provider_key: string, domain: string, device_id: DeviceId, device_info: DeviceInfo, device_class: string, id_conflicts: number[], presence: DevicePresence }) {
+  const { provider_key, domain, device_id, device_info, id_conflicts, presence } = props;
   return <Nav.Item className="device-list-device">
      <Nav.Link eventKey={`device-${provider_key}-${domain}-${JSON.stringify(device_id)}`}>
        {
@@ -136,7 +144,9 @@ export function DevicePillComponent(props: { provider_key: string, domain: strin
          </React.Fragment> : <React.Fragment>
            { device_info.device_id != undefined && `#${device_info.device_id}` } &nbsp;
            { renderDeviceType(device_info.device_type) } &nbsp;
-           { device_info.name != undefined && `(${device_info.name})` }
+           { device_info.name != undefined && `(${device_info.name})` } &nbsp;
+           { id_conflicts.length > 0 && <span className="text-danger" title={`Shares its ID with ${id_conflicts.map(s => `0x${s.toString(16)}`).join(", ")}`}>ID CONFLICT</span> }
+           { !presence.online && <span className="text-muted" title={`Last seen ${new Date(presence.last_seen).toLocaleTimeString()}`}>OFFLINE</span> }
            <br />
            <span className="tip">
              { domain } &nbsp;
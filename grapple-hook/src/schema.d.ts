@@ -5,235 +5,303 @@
  * and run json-schema-to-typescript to regenerate this file.
  */
 
-export type FirmwareUpgradeDeviceRequest =
+/**
+ * Something that happened to a device, pushed to subscribers as it happens so they don't have to poll the device list or each device's status.
+ */
+export type DeviceEvent =
   | {
       data: {
-        data: number[];
+        device_class: string;
+        domain: string;
+        id: DeviceId;
+        info: DeviceInfo;
       };
-      method: "do_field_upgrade";
-    }
-  | {
-      data: {};
-      method: "progress";
-    }
-  | {
-      data: {};
-      method: "get_firmware_url";
-    };
-export type FirmwareUpgradeDeviceResponse =
-  | {
-      data: null;
-      method: "do_field_upgrade";
+      event: "Added";
     }
   | {
-      data: number | null;
-      method: "progress";
+      data: {
+        domain: string;
+        id: DeviceId;
+      };
+      event: "Removed";
     }
   | {
-      data: string | null;
-      method: "get_firmware_url";
-    };
-export type FlexiCanRequest =
-  | {
-      data: {};
-      method: "start_field_upgrade";
+      data: {
+        domain: string;
+        id: DeviceId;
+        online: boolean;
+      };
+      event: "PresenceChanged";
     }
   | {
       data: {
-        msg: GrappleDeviceRequest;
+        device_class: string;
+        domain: string;
+        is_dfu: boolean;
+        serial: number;
       };
-      method: "grapple";
+      event: "DfuTransition";
     }
   | {
-      data: {};
-      method: "status";
-    };
-export type GrappleDeviceRequest =
-  | {
-      data: {};
-      method: "blink";
+      data: {
+        domain: string;
+        id: DeviceId;
+        new_id?: number | null;
+        old_id?: number | null;
+      };
+      event: "IdChanged";
     }
   | {
       data: {
-        id: number;
+        domain: string;
+        id: DeviceId;
+        new_name?: string | null;
+        old_name?: string | null;
       };
-      method: "set_id";
+      event: "NameChanged";
     }
   | {
       data: {
-        name: string;
+        domain: string;
+        id: DeviceId;
+        message: TaggedGrappleMessage;
       };
-      method: "set_name";
+      event: "StatusFrame";
+    };
+export type DeviceId =
+  | {
+      Dfu: number;
     }
   | {
-      data: {};
-      method: "commit_to_eeprom";
+      Serial: number;
     };
-export type FlexiCanResponse =
+export type DeviceType =
+  | ("RoboRIO" | "Unknown")
   | {
-      data: null;
-      method: "start_field_upgrade";
-    }
+      Grapple: GrappleModelId;
+    };
+export type GrappleModelId = "LaserCan" | "SpiderLan" | "FlexiCAN" | "MitoCANdria";
+export type GrappleDeviceMessage =
   | {
-      data: GrappleDeviceResponse;
-      method: "grapple";
+      data: GrappleBroadcastMessage;
+      type: "Broadcast";
     }
   | {
-      data: FlexiCanStatus;
-      method: "status";
-    };
-export type GrappleDeviceResponse =
+      data: GrappleFirmwareMessage;
+      type: "FirmwareUpdate";
+    }
   | {
-      data: null;
-      method: "blink";
+      data: LaserCanMessage;
+      type: "DistanceSensor";
     }
   | {
-      data: null;
-      method: "set_id";
+      data: MitocandriaMessage;
+      type: "PowerDistributionModule";
     }
   | {
-      data: null;
-      method: "set_name";
+      data: FlexiCANMessage;
+      type: "IOBreakout";
     }
   | {
-      data: null;
-      method: "commit_to_eeprom";
+      data: MiscMessage;
+      type: "Misc";
     };
-export type LaserCanRequest =
+export type GrappleBroadcastMessage = {
+  data: GrappleDeviceInfo;
+  type: "DeviceInfo";
+};
+export type GrappleDeviceInfo =
   | {
-      data: {};
-      method: "start_field_upgrade";
+      type: "EnumerateRequest";
     }
   | {
       data: {
-        mode: LaserCanRangingMode;
+        is_dfu: boolean;
+        is_dfu_in_progress: boolean;
+        model_id: GrappleModelId;
+        name: string;
+        serial: number;
+        version: string;
       };
-      method: "set_range";
+      type: "EnumerateResponse";
     }
   | {
       data: {
-        roi: LaserCanRoi;
+        serial: number;
       };
-      method: "set_roi";
+      type: "Blink";
     }
   | {
       data: {
-        budget: LaserCanTimingBudget;
+        name: string;
+        serial: number;
       };
-      method: "set_timing_budget";
+      type: "SetName";
     }
   | {
       data: {
-        msg: GrappleDeviceRequest;
+        serial: number;
       };
-      method: "grapple";
+      type: "CommitConfig";
     }
   | {
-      data: {};
-      method: "status";
+      data: {
+        new_id: number;
+        serial: number;
+      };
+      type: "SetId";
     }
   | {
-      data: {};
-      method: "check_for_new_firmware";
-    };
-export type LaserCanRangingMode = "Short" | "Long";
-export type LaserCanTimingBudget = "TB20ms" | "TB33ms" | "TB50ms" | "TB100ms";
-export type LaserCanResponse =
-  | {
-      data: null;
-      method: "start_field_upgrade";
+      type: "ArbitrationRequest";
     }
   | {
-      data: null;
-      method: "set_range";
+      type: "ArbitrationReject";
+    };
+export type GrappleFirmwareMessage =
+  | {
+      data: {
+        serial: number;
+      };
+      type: "StartFieldUpgrade";
     }
   | {
-      data: null;
-      method: "set_roi";
+      data: number[];
+      type: "UpdatePart";
     }
   | {
-      data: null;
-      method: "set_timing_budget";
+      type: "UpdatePartAck";
     }
   | {
-      data: GrappleDeviceResponse;
-      method: "grapple";
+      type: "UpdateDone";
     }
   | {
-      data: LaserCanStatus;
-      method: "status";
+      data: RequestFor_UpdatePartV2PayloadAnd_ResultOf_NullOr_GrappleError;
+      type: "UpdatePartV2";
     }
   | {
-      data: LightReleaseResponse | null;
-      method: "check_for_new_firmware";
+      data: RequestFor_NullAnd_ResultOf_FlashParametersOr_GrappleError;
+      type: "GetFlashParameters";
     };
-export type MitocandriaRequest =
+export type RequestFor_UpdatePartV2PayloadAnd_ResultOf_NullOr_GrappleError =
   | {
-      data: {};
-      method: "start_field_upgrade";
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
     }
   | {
-      data: {
-        channel: MitocandriaSwitchableChannelRequest;
-      };
-      method: "set_switchable_channel";
-    }
+      data: UpdatePartV2Payload;
+      type: "Request";
+    };
+export type ResultOf_NullOr_GrappleError =
   | {
-      data: {
-        channel: MitocandriaAdjustableChannelRequest;
-      };
-      method: "set_adjustable_channel";
+      Ok: null;
     }
   | {
-      data: {};
-      method: "calibrate_adjustable_channel";
+      Err: GrappleError;
+    };
+export type GrappleError =
+  | {
+      data: string;
+      type: "ParameterOutOfBounds";
     }
   | {
-      data: {
-        msg: GrappleDeviceRequest;
-      };
-      method: "grapple";
+      data: string;
+      type: "FailedAssertion";
     }
   | {
-      data: {};
-      method: "status";
+      data: string;
+      type: "TimedOut";
     }
   | {
-      data: {};
-      method: "check_for_new_firmware";
+      data: string;
+      type: "Generic";
     };
-export type MitocandriaResponse =
+export type RequestFor_NullAnd_ResultOf_FlashParametersOr_GrappleError =
   | {
-      data: null;
-      method: "start_field_upgrade";
+      data: ResultOf_FlashParametersOr_GrappleError;
+      type: "Ack";
     }
   | {
       data: null;
-      method: "set_switchable_channel";
+      type: "Request";
+    };
+export type ResultOf_FlashParametersOr_GrappleError =
+  | {
+      Ok: FlashParameters;
     }
   | {
-      data: null;
-      method: "set_adjustable_channel";
+      Err: GrappleError;
+    };
+export type LaserCanMessage =
+  | {
+      data: LaserCanMeasurement;
+      type: "Measurement";
     }
   | {
-      data: null;
-      method: "calibrate_adjustable_channel";
+      data: RequestFor_LaserCanRangingModeAnd_ResultOf_NullOr_GrappleError;
+      type: "SetRange";
     }
   | {
-      data: GrappleDeviceResponse;
-      method: "grapple";
+      data: RequestFor_LaserCanRoiAnd_ResultOf_NullOr_GrappleError;
+      type: "SetRoi";
     }
   | {
-      data: MitocandriaStatus;
-      method: "status";
+      data: RequestFor_LaserCanTimingBudgetAnd_ResultOf_NullOr_GrappleError;
+      type: "SetTimingBudget";
     }
   | {
-      data: LightReleaseResponse | null;
-      method: "check_for_new_firmware";
+      data: RequestForUint16And_ResultOf_NullOr_GrappleError;
+      type: "SetLedThreshold";
     };
-export type MitocandriaChannelStatus =
-  | {
-      data: {
+export type LaserCanTimingBudget = "TB20ms" | "TB33ms" | "TB50ms" | "TB100ms";
+export type LaserCanRangingMode = "Short" | "Long";
+export type RequestFor_LaserCanRangingModeAnd_ResultOf_NullOr_GrappleError =
+  | {
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
+    }
+  | {
+      data: LaserCanRangingMode;
+      type: "Request";
+    };
+export type RequestFor_LaserCanRoiAnd_ResultOf_NullOr_GrappleError =
+  | {
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
+    }
+  | {
+      data: LaserCanRoi;
+      type: "Request";
+    };
+export type RequestFor_LaserCanTimingBudgetAnd_ResultOf_NullOr_GrappleError =
+  | {
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
+    }
+  | {
+      data: LaserCanTimingBudget;
+      type: "Request";
+    };
+export type RequestForUint16And_ResultOf_NullOr_GrappleError =
+  | {
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
+    }
+  | {
+      data: number;
+      type: "Request";
+    };
+export type MitocandriaMessage =
+  | {
+      data: MitocandriaStatusFrame;
+      type: "StatusFrame";
+    }
+  | {
+      data: MitocandriaChannelRequest;
+      type: "ChannelRequest";
+    };
+export type MitocandriaChannelStatus =
+  | {
+      data: {
         current: number;
         enabled: boolean;
       };
@@ -254,674 +322,1230 @@ export type MitocandriaChannelStatus =
       };
       type: "Adjustable";
     };
-export type OldVersionDeviceRequest =
+export type MitocandriaChannelRequest =
   | {
-      data: {};
-      method: "start_field_upgrade";
+      data: RequestFor_MitocandriaSwitchableChannelRequestAnd_ResultOf_NullOr_GrappleError;
+      type: "SetSwitchableChannel";
     }
   | {
-      data: {};
-      method: "get_error";
+      data: RequestFor_MitocandriaAdjustableChannelRequestAnd_ResultOf_NullOr_GrappleError;
+      type: "SetAdjustableChannel";
     }
   | {
-      data: {};
-      method: "get_firmware_url";
+      data: RequestFor_MitocandriaAdjustableChannelCalibrationRequestAnd_ResultOf_NullOr_GrappleError;
+      type: "CalibrateAdjChannel";
     }
   | {
-      data: {
-        msg: GrappleDeviceRequest;
-      };
-      method: "grapple";
+      data: RequestFor_NullAnd_ResultOf_NullOr_GrappleError;
+      type: "StartAutoCalibrate";
     };
-export type OldVersionDeviceResponse =
+export type RequestFor_MitocandriaSwitchableChannelRequestAnd_ResultOf_NullOr_GrappleError =
   | {
-      data: null;
-      method: "start_field_upgrade";
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
     }
   | {
-      data: string;
-      method: "get_error";
-    }
+      data: MitocandriaSwitchableChannelRequest;
+      type: "Request";
+    };
+export type RequestFor_MitocandriaAdjustableChannelRequestAnd_ResultOf_NullOr_GrappleError =
   | {
-      data: string | null;
-      method: "get_firmware_url";
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
     }
   | {
-      data: GrappleDeviceResponse;
-      method: "grapple";
+      data: MitocandriaAdjustableChannelRequest;
+      type: "Request";
     };
-export type ProviderManagerRequest =
-  | {
-      data: {
-        address: string;
-      };
-      method: "delete";
-    }
+export type RequestFor_MitocandriaAdjustableChannelCalibrationRequestAnd_ResultOf_NullOr_GrappleError =
   | {
-      data: {
-        address: string;
-        msg: WrappedDeviceProviderRequest;
-      };
-      method: "provider";
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
     }
   | {
-      data: {};
-      method: "providers";
+      data: MitocandriaAdjustableChannelCalibrationRequest;
+      type: "Request";
     };
-export type WrappedDeviceProviderRequest =
+export type RequestFor_NullAnd_ResultOf_NullOr_GrappleError =
   | {
-      data: {};
-      method: "connect";
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
     }
   | {
-      data: {};
-      method: "disconnect";
+      data: null;
+      type: "Request";
+    };
+export type FlexiCANMessage = {
+  data: BridgeMessages;
+  type: "Bridge";
+};
+export type BridgeMessages =
+  | {
+      data: RequestForUint8And_ResultOf_StringOr_GrappleError;
+      type: "GetChannelName";
     }
   | {
-      data: {};
-      method: "info";
+      data: RequestForUint8And_ResultOf_NullOr_GrappleError;
+      type: "StartBridge";
     }
   | {
-      data: {
-        req: DeviceManagerRequest;
-      };
-      method: "device_manager_call";
+      data: RequestForUint8And_ResultOf_NullOr_GrappleError;
+      type: "StopBridge";
     }
   | {
-      data: {
-        req: unknown;
-      };
-      method: "call";
+      data: EncapsulatedMesssage;
+      type: "BridgeMessage";
     };
-export type DeviceManagerRequest =
+export type RequestForUint8And_ResultOf_StringOr_GrappleError =
   | {
-      data: {
-        data: unknown;
-        device_id: DeviceId;
-        domain: string;
-      };
-      method: "call";
+      data: ResultOf_StringOr_GrappleError;
+      type: "Ack";
     }
   | {
-      data: {};
-      method: "devices";
+      data: number;
+      type: "Request";
     };
-export type DeviceId =
+export type ResultOf_StringOr_GrappleError =
   | {
-      Dfu: number;
+      Ok: string;
     }
   | {
-      Serial: number;
+      Err: GrappleError;
     };
-export type ProviderManagerResponse =
+export type RequestForUint8And_ResultOf_NullOr_GrappleError =
   | {
-      data: null;
-      method: "delete";
+      data: ResultOf_NullOr_GrappleError;
+      type: "Ack";
     }
   | {
-      data: WrappedDeviceProviderResponse;
-      method: "provider";
+      data: number;
+      type: "Request";
+    };
+export type MiscMessage =
+  | {
+      data: number[];
+      type: "MiscMessage";
     }
   | {
-      data: {
-        [k: string]: ProviderInfo;
-      };
-      method: "providers";
+      data: JMSMessage;
+      type: "JMS";
     };
-export type WrappedDeviceProviderResponse =
+export type JMSMessage =
   | {
-      data: null;
-      method: "connect";
+      data: JMSElectronicsStatus;
+      type: "Status";
     }
   | {
-      data: null;
-      method: "disconnect";
+      data: JMSRole;
+      type: "SetRole";
     }
   | {
-      data: ProviderInfo;
-      method: "info";
+      data: JMSElectronicsUpdate;
+      type: "Update";
     }
   | {
-      data: DeviceManagerResponse;
-      method: "device_manager_call";
-    }
+      type: "Blink";
+    };
+export type JMSCardStatus =
+  | "Lighting"
   | {
-      data: unknown;
-      method: "call";
+      /**
+       * @minItems 8
+       * @maxItems 8
+       */
+      IO: [boolean, boolean, boolean, boolean, boolean, boolean, boolean, boolean];
     };
-export type DeviceManagerResponse =
+export type JMSRole =
+  | ("ScoringTable" | "TimerRed" | "TimerBlue")
   | {
-      data: unknown;
-      method: "call";
+      Red: number;
     }
   | {
-      data: {
-        [k: string]: [DeviceId, DeviceInfo, string][];
-      };
-      method: "devices";
-    };
-export type DeviceType =
-  | ("RoboRIO" | "Unknown")
-  | {
-      Grapple: GrappleModelId;
+      Blue: number;
     };
-export type GrappleModelId = "LaserCan" | "SpiderLan" | "FlexiCAN" | "MitoCANdria";
-export type RoboRioDaemonRequest =
+export type JMSCardUpdate =
   | {
-      data: {};
-      method: "status";
+      /**
+       * @minItems 0
+       * @maxItems 0
+       */
+      IO: [];
     }
   | {
-      data: {
-        use_daemon: boolean;
+      Lighting: {
+        back_background: Pattern;
+        background: Pattern;
+        bottom_bar: Pattern;
+        text: string;
+        text_back: string;
+        text_back_colour: Colour;
+        text_colour: Colour;
+        top_bar: Pattern;
       };
-      method: "set_use_daemon";
+    };
+export type Pattern =
+  | "Blank"
+  | {
+      Solid: Colour;
     }
   | {
-      data: {
-        address: string;
-      };
-      method: "set_address";
-    }
-  | {
-      data: {
-        req: CanLogRequest;
-      };
-      method: "canlog_call";
-    };
-export type CanLogRequest =
-  | {
-      data: {
-        enabled: boolean;
-      };
-      method: "set_log_enabled";
+      /**
+       * @minItems 2
+       * @maxItems 2
+       */
+      DiagonalStripes: [Colour, Colour];
     }
   | {
-      data: {};
-      method: "clear";
+      /**
+       * @minItems 3
+       * @maxItems 3
+       */
+      FillLeft: [Colour, Colour, number];
     }
   | {
-      data: {
-        seq: number;
-      };
-      method: "read_after";
-    }
+      /**
+       * @minItems 3
+       * @maxItems 3
+       */
+      FillRight: [Colour, Colour, number];
+    };
+export type FirmwareUpgradeDeviceRequest =
   | {
       data: {
-        filters: Filter[];
+        data: number[];
       };
-      method: "set_filters";
+      method: "do_field_upgrade";
     }
   | {
       data: {
-        data: number[];
-        id: MessageId;
-      };
-      method: "send_raw";
-    };
-export type Filter =
-  | "GrappleOnly"
-  | {
-      IdMask: {
-        id: MessageId;
-        mask: MessageId;
+        release: LightReleaseResponse;
       };
+      method: "do_field_upgrade_from_release";
     }
   | {
-      IdMaskRaw: {
-        id: number;
-        mask: number;
-      };
+      data: {};
+      method: "resume_field_upgrade";
     }
   | {
-      BodySize: {
-        max: number;
-        min: number;
-      };
-    };
-export type RoboRioDaemonResponse =
-  | {
-      data: RoboRIOStatus;
-      method: "status";
+      data: {};
+      method: "can_resume_field_upgrade";
     }
   | {
-      data: null;
-      method: "set_use_daemon";
+      data: {};
+      method: "progress";
     }
   | {
-      data: null;
-      method: "set_address";
+      data: {};
+      method: "upgrade_result";
     }
   | {
-      data: CanLogResponse;
-      method: "canlog_call";
+      data: {};
+      method: "get_firmware_url";
     };
-export type CanLogResponse =
-  | {
-      data: null;
-      method: "set_log_enabled";
-    }
+export type FirmwareUpgradeDeviceResponse =
   | {
       data: null;
-      method: "clear";
-    }
-  | {
-      data: MailboxItem[];
-      method: "read_after";
+      method: "do_field_upgrade";
     }
   | {
       data: null;
-      method: "set_filters";
+      method: "do_field_upgrade_from_release";
     }
   | {
       data: null;
-      method: "send_raw";
-    };
-export type GrappleDeviceMessage =
-  | {
-      data: GrappleBroadcastMessage;
-      type: "Broadcast";
-    }
-  | {
-      data: GrappleFirmwareMessage;
-      type: "FirmwareUpdate";
+      method: "resume_field_upgrade";
     }
   | {
-      data: LaserCanMessage;
-      type: "DistanceSensor";
+      data: boolean;
+      method: "can_resume_field_upgrade";
     }
   | {
-      data: MitocandriaMessage;
-      type: "PowerDistributionModule";
+      data: number | null;
+      method: "progress";
     }
   | {
-      data: FlexiCANMessage;
-      type: "IOBreakout";
+      data: FirmwareUpgradeResult | null;
+      method: "upgrade_result";
     }
   | {
-      data: MiscMessage;
-      type: "Misc";
+      data: string | null;
+      method: "get_firmware_url";
     };
-export type GrappleBroadcastMessage = {
-  data: GrappleDeviceInfo;
-  type: "DeviceInfo";
-};
-export type GrappleDeviceInfo =
+export type FirmwareUpgradeResult =
   | {
-      type: "EnumerateRequest";
+      result: "Success";
     }
   | {
       data: {
-        is_dfu: boolean;
-        is_dfu_in_progress: boolean;
-        model_id: GrappleModelId;
-        name: string;
-        serial: number;
-        version: string;
+        error: FirmwareUpgradeError;
       };
-      type: "EnumerateResponse";
-    }
+      result: "Failed";
+    };
+export type FirmwareUpgradeError =
   | {
       data: {
-        serial: number;
+        chunk: number;
+        offset: number;
       };
-      type: "Blink";
+      type: "Timeout";
     }
   | {
       data: {
-        name: string;
-        serial: number;
+        chunk: number;
+        error: string;
+        offset: number;
       };
-      type: "SetName";
+      type: "Nack";
     }
   | {
       data: {
-        serial: number;
+        message: string;
       };
-      type: "CommitConfig";
+      type: "Other";
+    };
+export type FlexiCanRequest =
+  | {
+      data: {};
+      method: "start_field_upgrade";
     }
   | {
       data: {
-        new_id: number;
-        serial: number;
+        msg: GrappleDeviceRequest;
       };
-      type: "SetId";
+      method: "grapple";
     }
   | {
-      type: "ArbitrationRequest";
+      data: {};
+      method: "status";
     }
   | {
-      type: "ArbitrationReject";
-    };
-export type GrappleFirmwareMessage =
+      data: {
+        channel: number;
+      };
+      method: "get_channel_name";
+    }
   | {
       data: {
-        serial: number;
+        channel: number;
       };
-      type: "StartFieldUpgrade";
+      method: "start_bridge";
     }
   | {
-      data: number[];
-      type: "UpdatePart";
+      data: {
+        channel: number;
+      };
+      method: "stop_bridge";
     }
   | {
-      type: "UpdatePartAck";
+      data: {};
+      method: "check_for_new_firmware";
+    };
+export type GrappleDeviceRequest =
+  | {
+      data: {};
+      method: "blink";
     }
   | {
-      type: "UpdateDone";
+      data: {
+        id: number;
+      };
+      method: "set_id";
     }
   | {
-      data: RequestFor_UpdatePartV2PayloadAnd_ResultOf_NullOr_GrappleError;
-      type: "UpdatePartV2";
+      data: {
+        name: string;
+      };
+      method: "set_name";
     }
   | {
-      data: RequestFor_NullAnd_ResultOf_FlashParametersOr_GrappleError;
-      type: "GetFlashParameters";
+      data: {};
+      method: "commit_to_eeprom";
     };
-export type RequestFor_UpdatePartV2PayloadAnd_ResultOf_NullOr_GrappleError =
+export type FlexiCanResponse =
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: null;
+      method: "start_field_upgrade";
     }
   | {
-      data: UpdatePartV2Payload;
-      type: "Request";
-    };
-export type ResultOf_NullOr_GrappleError =
-  | {
-      Ok: null;
+      data: GrappleDeviceResponse;
+      method: "grapple";
     }
   | {
-      Err: GrappleError;
-    };
-export type GrappleError =
+      data: FlexiCanStatus;
+      method: "status";
+    }
   | {
       data: string;
-      type: "ParameterOutOfBounds";
+      method: "get_channel_name";
     }
   | {
-      data: string;
-      type: "FailedAssertion";
+      data: null;
+      method: "start_bridge";
     }
   | {
-      data: string;
-      type: "TimedOut";
+      data: null;
+      method: "stop_bridge";
     }
   | {
-      data: string;
-      type: "Generic";
+      data: LightReleaseResponse | null;
+      method: "check_for_new_firmware";
     };
-export type RequestFor_NullAnd_ResultOf_FlashParametersOr_GrappleError =
+export type GrappleDeviceResponse =
   | {
-      data: ResultOf_FlashParametersOr_GrappleError;
-      type: "Ack";
+      data: null;
+      method: "blink";
     }
   | {
       data: null;
-      type: "Request";
-    };
-export type ResultOf_FlashParametersOr_GrappleError =
+      method: "set_id";
+    }
   | {
-      Ok: FlashParameters;
+      data: null;
+      method: "set_name";
     }
   | {
-      Err: GrappleError;
+      data: null;
+      method: "commit_to_eeprom";
     };
-export type LaserCanMessage =
+export type LaserCanRequest =
   | {
-      data: LaserCanMeasurement;
-      type: "Measurement";
+      data: {};
+      method: "start_field_upgrade";
     }
   | {
-      data: RequestFor_LaserCanRangingModeAnd_ResultOf_NullOr_GrappleError;
-      type: "SetRange";
+      data: {
+        mode: LaserCanRangingMode;
+      };
+      method: "set_range";
     }
   | {
-      data: RequestFor_LaserCanRoiAnd_ResultOf_NullOr_GrappleError;
-      type: "SetRoi";
+      data: {
+        roi: LaserCanRoi;
+      };
+      method: "set_roi";
     }
   | {
-      data: RequestFor_LaserCanTimingBudgetAnd_ResultOf_NullOr_GrappleError;
-      type: "SetTimingBudget";
-    }
+      data: {
+        budget: LaserCanTimingBudget;
+      };
+      method: "set_timing_budget";
+    }
+  | {
+      data: {
+        msg: GrappleDeviceRequest;
+      };
+      method: "grapple";
+    }
+  | {
+      data: {};
+      method: "status";
+    }
+  | {
+      data: {};
+      method: "history";
+    }
+  | {
+      data: {
+        length: number;
+      };
+      method: "set_history_length";
+    }
+  | {
+      data: {};
+      method: "clear_history";
+    }
+  | {
+      data: {};
+      method: "get_config";
+    }
+  | {
+      data: {};
+      method: "check_for_new_firmware";
+    };
+export type LaserCanResponse =
+  | {
+      data: null;
+      method: "start_field_upgrade";
+    }
+  | {
+      data: null;
+      method: "set_range";
+    }
+  | {
+      data: null;
+      method: "set_roi";
+    }
+  | {
+      data: null;
+      method: "set_timing_budget";
+    }
+  | {
+      data: GrappleDeviceResponse;
+      method: "grapple";
+    }
+  | {
+      data: LaserCanStatus;
+      method: "status";
+    }
+  | {
+      data: LaserCanHistory;
+      method: "history";
+    }
+  | {
+      data: null;
+      method: "set_history_length";
+    }
+  | {
+      data: null;
+      method: "clear_history";
+    }
+  | {
+      data: LaserCanConfig;
+      method: "get_config";
+    }
+  | {
+      data: LightReleaseResponse | null;
+      method: "check_for_new_firmware";
+    };
+export type MitocandriaRequest =
+  | {
+      data: {};
+      method: "start_field_upgrade";
+    }
+  | {
+      data: {
+        channel: MitocandriaSwitchableChannelRequest;
+      };
+      method: "set_switchable_channel";
+    }
+  | {
+      data: {
+        channel: MitocandriaAdjustableChannelRequest;
+      };
+      method: "set_adjustable_channel";
+    }
+  | {
+      data: {};
+      method: "calibrate_adjustable_channel";
+    }
+  | {
+      data: {
+        msg: GrappleDeviceRequest;
+      };
+      method: "grapple";
+    }
+  | {
+      data: {};
+      method: "status";
+    }
+  | {
+      data: {};
+      method: "history";
+    }
+  | {
+      data: {
+        window_s: number;
+      };
+      method: "set_history_window";
+    }
+  | {
+      data: {};
+      method: "clear_history";
+    }
+  | {
+      data: {
+        path: string;
+      };
+      method: "export_history_csv";
+    }
+  | {
+      data: {};
+      method: "check_for_new_firmware";
+    };
+export type MitocandriaResponse =
+  | {
+      data: null;
+      method: "start_field_upgrade";
+    }
+  | {
+      data: null;
+      method: "set_switchable_channel";
+    }
+  | {
+      data: null;
+      method: "set_adjustable_channel";
+    }
+  | {
+      data: null;
+      method: "calibrate_adjustable_channel";
+    }
+  | {
+      data: GrappleDeviceResponse;
+      method: "grapple";
+    }
+  | {
+      data: MitocandriaStatus;
+      method: "status";
+    }
+  | {
+      data: MitocandriaChannelHistory[];
+      method: "history";
+    }
+  | {
+      data: null;
+      method: "set_history_window";
+    }
+  | {
+      data: null;
+      method: "clear_history";
+    }
+  | {
+      data: null;
+      method: "export_history_csv";
+    }
+  | {
+      data: LightReleaseResponse | null;
+      method: "check_for_new_firmware";
+    };
+export type OldVersionDeviceRequest =
+  | {
+      data: {};
+      method: "start_field_upgrade";
+    }
+  | {
+      data: {};
+      method: "get_error";
+    }
+  | {
+      data: {};
+      method: "get_firmware_url";
+    }
+  | {
+      data: {
+        msg: GrappleDeviceRequest;
+      };
+      method: "grapple";
+    };
+export type OldVersionDeviceResponse =
+  | {
+      data: null;
+      method: "start_field_upgrade";
+    }
+  | {
+      data: string;
+      method: "get_error";
+    }
+  | {
+      data: string | null;
+      method: "get_firmware_url";
+    }
+  | {
+      data: GrappleDeviceResponse;
+      method: "grapple";
+    };
+export type ProviderManagerRequest =
+  | {
+      data: {
+        address: string;
+      };
+      method: "delete";
+    }
+  | {
+      data: {
+        format: RecordingFormat;
+        path: string;
+      };
+      method: "add_replay";
+    }
+  | {
+      data: {};
+      method: "add_simulator";
+    }
+  | {
+      data: {};
+      method: "update_source";
+    }
+  | {
+      data: {
+        config: UpdateSourceConfig;
+      };
+      method: "set_update_source";
+    }
+  | {
+      data: {
+        path: string;
+      };
+      method: "import_firmware_bundles";
+    }
+  | {
+      data: {
+        address: string;
+        msg: WrappedDeviceProviderRequest;
+      };
+      method: "provider";
+    }
+  | {
+      data: {};
+      method: "providers";
+    };
+export type RecordingFormat = "Candump" | "Pcap" | "Json";
+export type UpdateChannel = "Stable" | "Prerelease";
+export type WrappedDeviceProviderRequest =
+  | {
+      data: {};
+      method: "connect";
+    }
+  | {
+      data: {};
+      method: "disconnect";
+    }
+  | {
+      data: {};
+      method: "info";
+    }
+  | {
+      data: {
+        req: DeviceManagerRequest;
+      };
+      method: "device_manager_call";
+    }
+  | {
+      data: {
+        req: unknown;
+      };
+      method: "call";
+    };
+export type DeviceManagerRequest =
+  | {
+      data: {
+        data: unknown;
+        device_id: DeviceId;
+        domain: string;
+        retry?: RetryPolicy | null;
+      };
+      method: "call";
+    }
+  | {
+      data: {
+        data: unknown;
+        device_id: DeviceId;
+        domain: string;
+        retry?: RetryPolicy | null;
+      };
+      method: "call_with_stats";
+    }
+  | {
+      data: {};
+      method: "devices";
+    }
+  | {
+      data: {};
+      method: "age_off";
+    }
+  | {
+      data: {
+        config: AgeOffConfig;
+      };
+      method: "set_age_off";
+    }
+  | {
+      data: {};
+      method: "retry_policy";
+    }
+  | {
+      data: {
+        policy: RetryPolicy;
+      };
+      method: "set_retry_policy";
+    }
+  | {
+      data: {};
+      method: "resolve_id_conflicts";
+    }
+  | {
+      data: {};
+      method: "snapshot_config";
+    }
+  | {
+      data: {
+        format: ConfigFormat;
+        path: string;
+      };
+      method: "export_config";
+    }
+  | {
+      data: {
+        commit: boolean;
+        snapshot: ConfigSnapshot;
+      };
+      method: "apply_config";
+    }
+  | {
+      data: {
+        commit: boolean;
+        format: ConfigFormat;
+        path: string;
+      };
+      method: "import_config";
+    }
+  | {
+      data: {
+        data: number[];
+        model: GrappleModelId;
+      };
+      method: "start_bulk_field_upgrade";
+    }
+  | {
+      data: {};
+      method: "bulk_field_upgrade_status";
+    }
+  | {
+      data: {
+        data: number[];
+      };
+      method: "inspect_firmware_bundle";
+    };
+export type ConfigFormat = "Json" | "Toml";
+/**
+ * Device-specific settings, beyond the ID and name common to all Grapple devices.
+ */
+export type DeviceConfig =
+  | {
+      budget: LaserCanTimingBudget;
+      mode: LaserCanRangingMode;
+      roi: LaserCanRoi;
+      type: "LaserCan";
+    }
+  | {
+      channels: MitocandriaChannelConfig[];
+      type: "Mitocandria";
+    };
+export type ProviderManagerResponse =
+  | {
+      data: null;
+      method: "delete";
+    }
+  | {
+      data: string;
+      method: "add_replay";
+    }
+  | {
+      data: string;
+      method: "add_simulator";
+    }
+  | {
+      data: UpdateSourceConfig;
+      method: "update_source";
+    }
+  | {
+      data: null;
+      method: "set_update_source";
+    }
+  | {
+      data: ImportedFirmwareBundle[];
+      method: "import_firmware_bundles";
+    }
+  | {
+      data: WrappedDeviceProviderResponse;
+      method: "provider";
+    }
+  | {
+      data: {
+        [k: string]: ProviderInfo;
+      };
+      method: "providers";
+    };
+export type WrappedDeviceProviderResponse =
+  | {
+      data: null;
+      method: "connect";
+    }
+  | {
+      data: null;
+      method: "disconnect";
+    }
+  | {
+      data: ProviderInfo;
+      method: "info";
+    }
+  | {
+      data: DeviceManagerResponse;
+      method: "device_manager_call";
+    }
+  | {
+      data: unknown;
+      method: "call";
+    };
+export type DeviceManagerResponse =
+  | {
+      data: unknown;
+      method: "call";
+    }
+  | {
+      data: CallReport;
+      method: "call_with_stats";
+    }
+  | {
+      data: {
+        [k: string]: [DeviceId, DeviceInfo, string, number[], DevicePresence][];
+      };
+      method: "devices";
+    }
+  | {
+      data: AgeOffConfig;
+      method: "age_off";
+    }
+  | {
+      data: null;
+      method: "set_age_off";
+    }
+  | {
+      data: RetryPolicy;
+      method: "retry_policy";
+    }
+  | {
+      data: null;
+      method: "set_retry_policy";
+    }
+  | {
+      data: IdReassignment[];
+      method: "resolve_id_conflicts";
+    }
+  | {
+      data: ConfigSnapshot;
+      method: "snapshot_config";
+    }
+  | {
+      data: ConfigSnapshot;
+      method: "export_config";
+    }
+  | {
+      data: ConfigApplyResult[];
+      method: "apply_config";
+    }
+  | {
+      data: ConfigApplyResult[];
+      method: "import_config";
+    }
+  | {
+      data: BulkUpgradeStatus;
+      method: "start_bulk_field_upgrade";
+    }
+  | {
+      data: BulkUpgradeStatus | null;
+      method: "bulk_field_upgrade_status";
+    }
+  | {
+      data: FirmwareBundleInfo;
+      method: "inspect_firmware_bundle";
+    };
+export type BulkUpgradeState =
+  | {
+      state: "Pending";
+    }
+  | {
+      state: "EnteringDfu";
+    }
+  | {
+      data: {
+        progress: number;
+      };
+      state: "Flashing";
+    }
+  | {
+      state: "Rebooting";
+    }
+  | {
+      data: {
+        version?: string | null;
+      };
+      state: "Success";
+    }
+  | {
+      data: {
+        error: FirmwareUpgradeError;
+      };
+      state: "Failed";
+    };
+export type ReplayRequest =
+  | {
+      data: {};
+      method: "status";
+    }
   | {
-      data: RequestForUint16And_ResultOf_NullOr_GrappleError;
-      type: "SetLedThreshold";
+      data: {
+        mode: ReplayMode;
+      };
+      method: "set_mode";
+    }
+  | {
+      data: {
+        count: number;
+      };
+      method: "step";
     };
-export type RequestFor_LaserCanRangingModeAnd_ResultOf_NullOr_GrappleError =
+export type ReplayMode =
+  | ("RealTime" | "Step")
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      Accelerated: {
+        speed: number;
+      };
+    };
+export type ReplayResponse =
+  | {
+      data: ReplayStatus;
+      method: "status";
     }
   | {
-      data: LaserCanRangingMode;
-      type: "Request";
+      data: null;
+      method: "set_mode";
+    }
+  | {
+      data: null;
+      method: "step";
     };
-export type RequestFor_LaserCanRoiAnd_ResultOf_NullOr_GrappleError =
+export type RoboRioDaemonRequest =
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: {};
+      method: "status";
     }
   | {
-      data: LaserCanRoi;
-      type: "Request";
-    };
-export type RequestFor_LaserCanTimingBudgetAnd_ResultOf_NullOr_GrappleError =
+      data: {
+        use_daemon: boolean;
+      };
+      method: "set_use_daemon";
+    }
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: {
+        address: string;
+      };
+      method: "set_address";
     }
   | {
-      data: LaserCanTimingBudget;
-      type: "Request";
+      data: {
+        req: CanLogRequest;
+      };
+      method: "canlog_call";
     };
-export type RequestForUint16And_ResultOf_NullOr_GrappleError =
+export type CanLogRequest =
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: {
+        enabled: boolean;
+      };
+      method: "set_log_enabled";
     }
   | {
-      data: number;
-      type: "Request";
-    };
-export type MitocandriaMessage =
+      data: {};
+      method: "clear";
+    }
   | {
-      data: MitocandriaStatusFrame;
-      type: "StatusFrame";
+      data: {
+        seq: number;
+      };
+      method: "read_after";
     }
   | {
-      data: MitocandriaChannelRequest;
-      type: "ChannelRequest";
-    };
-export type MitocandriaChannelRequest =
+      data: {
+        filters: Filter[];
+      };
+      method: "set_filters";
+    }
   | {
-      data: RequestFor_MitocandriaSwitchableChannelRequestAnd_ResultOf_NullOr_GrappleError;
-      type: "SetSwitchableChannel";
+      data: {
+        data: number[];
+        id: MessageId;
+      };
+      method: "send_raw";
     }
   | {
-      data: RequestFor_MitocandriaAdjustableChannelRequestAnd_ResultOf_NullOr_GrappleError;
-      type: "SetAdjustableChannel";
+      data: {
+        format: RecordingFormat;
+        path: string;
+      };
+      method: "start_recording";
     }
   | {
-      data: RequestFor_MitocandriaAdjustableChannelCalibrationRequestAnd_ResultOf_NullOr_GrappleError;
-      type: "CalibrateAdjChannel";
+      data: {};
+      method: "stop_recording";
     }
   | {
-      data: RequestFor_NullAnd_ResultOf_NullOr_GrappleError;
-      type: "StartAutoCalibrate";
+      data: {};
+      method: "recording_status";
     };
-export type RequestFor_MitocandriaSwitchableChannelRequestAnd_ResultOf_NullOr_GrappleError =
+export type Filter =
+  | "GrappleOnly"
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      IdMask: {
+        id: MessageId;
+        mask: MessageId;
+      };
     }
   | {
-      data: MitocandriaSwitchableChannelRequest;
-      type: "Request";
+      IdMaskRaw: {
+        id: number;
+        mask: number;
+      };
+    }
+  | {
+      BodySize: {
+        max: number;
+        min: number;
+      };
     };
-export type RequestFor_MitocandriaAdjustableChannelRequestAnd_ResultOf_NullOr_GrappleError =
+export type RoboRioDaemonResponse =
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: RoboRIOStatus;
+      method: "status";
     }
   | {
-      data: MitocandriaAdjustableChannelRequest;
-      type: "Request";
-    };
-export type RequestFor_MitocandriaAdjustableChannelCalibrationRequestAnd_ResultOf_NullOr_GrappleError =
+      data: null;
+      method: "set_use_daemon";
+    }
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: null;
+      method: "set_address";
     }
   | {
-      data: MitocandriaAdjustableChannelCalibrationRequest;
-      type: "Request";
+      data: CanLogResponse;
+      method: "canlog_call";
     };
-export type RequestFor_NullAnd_ResultOf_NullOr_GrappleError =
+export type CanLogResponse =
   | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: null;
+      method: "set_log_enabled";
     }
   | {
       data: null;
-      type: "Request";
-    };
-export type FlexiCANMessage = {
-  data: BridgeMessages;
-  type: "Bridge";
-};
-export type BridgeMessages =
+      method: "clear";
+    }
   | {
-      data: RequestForUint8And_ResultOf_StringOr_GrappleError;
-      type: "GetChannelName";
+      data: MailboxItem[];
+      method: "read_after";
     }
   | {
-      data: RequestForUint8And_ResultOf_NullOr_GrappleError;
-      type: "StartBridge";
+      data: null;
+      method: "set_filters";
     }
   | {
-      data: RequestForUint8And_ResultOf_NullOr_GrappleError;
-      type: "StopBridge";
+      data: null;
+      method: "send_raw";
     }
   | {
-      data: EncapsulatedMesssage;
-      type: "BridgeMessage";
-    };
-export type RequestForUint8And_ResultOf_StringOr_GrappleError =
+      data: null;
+      method: "start_recording";
+    }
   | {
-      data: ResultOf_StringOr_GrappleError;
-      type: "Ack";
+      data: RecordingStatus | null;
+      method: "stop_recording";
     }
   | {
-      data: number;
-      type: "Request";
+      data: RecordingStatus | null;
+      method: "recording_status";
     };
-export type ResultOf_StringOr_GrappleError =
+export type SimulatorRequest =
   | {
-      Ok: string;
+      data: {};
+      method: "devices";
     }
   | {
-      Err: GrappleError;
-    };
-export type RequestForUint8And_ResultOf_NullOr_GrappleError =
-  | {
-      data: ResultOf_NullOr_GrappleError;
-      type: "Ack";
+      data: {
+        device_id: number;
+        model: GrappleModelId;
+        serial: number;
+      };
+      method: "add_device";
     }
   | {
-      data: number;
-      type: "Request";
-    };
-export type MiscMessage =
+      data: {
+        serial: number;
+      };
+      method: "remove_device";
+    }
   | {
-      data: number[];
-      type: "MiscMessage";
+      data: {
+        device: SimulatedDevice;
+      };
+      method: "set_device";
     }
   | {
-      data: JMSMessage;
-      type: "JMS";
+      data: {
+        serial: number;
+      };
+      method: "power_cycle";
     };
-export type JMSMessage =
-  | {
-      data: JMSElectronicsStatus;
-      type: "Status";
-    }
+export type SimulatedDeviceState =
   | {
-      data: JMSRole;
-      type: "SetRole";
+      LaserCan: {
+        budget: LaserCanTimingBudget;
+        distance_mm: number;
+        mode: LaserCanRangingMode;
+        roi: LaserCanRoi;
+      };
     }
   | {
-      data: JMSElectronicsUpdate;
-      type: "Update";
+      Mitocandria: {
+        /**
+         * @minItems 5
+         * @maxItems 5
+         */
+        channels: [
+          MitocandriaChannelStatus,
+          MitocandriaChannelStatus,
+          MitocandriaChannelStatus,
+          MitocandriaChannelStatus,
+          MitocandriaChannelStatus
+        ];
+      };
     }
   | {
-      type: "Blink";
+      FlexiCan: {
+        channels: SimulatedFlexiCanChannel[];
+      };
     };
-export type JMSCardStatus =
-  | "Lighting"
+export type SimulatorResponse =
   | {
-      /**
-       * @minItems 8
-       * @maxItems 8
-       */
-      IO: [boolean, boolean, boolean, boolean, boolean, boolean, boolean, boolean];
-    };
-export type JMSRole =
-  | ("ScoringTable" | "TimerRed" | "TimerBlue")
+      data: SimulatedDevice[];
+      method: "devices";
+    }
   | {
-      Red: number;
+      data: null;
+      method: "add_device";
     }
   | {
-      Blue: number;
-    };
-export type JMSCardUpdate =
+      data: null;
+      method: "remove_device";
+    }
   | {
-      /**
-       * @minItems 0
-       * @maxItems 0
-       */
-      IO: [];
+      data: null;
+      method: "set_device";
     }
   | {
-      Lighting: {
-        back_background: Pattern;
-        background: Pattern;
-        bottom_bar: Pattern;
-        text: string;
-        text_back: string;
-        text_back_colour: Colour;
-        text_colour: Colour;
-        top_bar: Pattern;
-      };
+      data: null;
+      method: "power_cycle";
     };
-export type Pattern =
-  | "Blank"
+export type UnknownGrappleDeviceRequest =
   | {
-      Solid: Colour;
+      data: {};
+      method: "start_field_upgrade";
     }
   | {
-      /**
-       * @minItems 2
-       * @maxItems 2
-       */
-      DiagonalStripes: [Colour, Colour];
-    }
+      data: {
+        msg: GrappleDeviceRequest;
+      };
+      method: "grapple";
+    };
+export type UnknownGrappleDeviceResponse =
   | {
-      /**
-       * @minItems 3
-       * @maxItems 3
-       */
-      FillLeft: [Colour, Colour, number];
+      data: null;
+      method: "start_field_upgrade";
     }
   | {
-      /**
-       * @minItems 3
-       * @maxItems 3
-       */
-      FillRight: [Colour, Colour, number];
+      data: GrappleDeviceResponse;
+      method: "grapple";
     };
 
 export interface MegaSchema {
+  device_event: ProviderDeviceEvent;
   firmware_req: FirmwareUpgradeDeviceRequest;
   firmware_rsp: FirmwareUpgradeDeviceResponse;
   flexican_req: FlexiCanRequest;
@@ -935,18 +1559,43 @@ export interface MegaSchema {
   old_version_rsp: OldVersionDeviceResponse;
   provider_manager_req: ProviderManagerRequest;
   provider_manager_rsp: ProviderManagerResponse;
+  replay_req: ReplayRequest;
+  replay_rsp: ReplayResponse;
   roborio_req: RoboRioDaemonRequest;
   roborio_rsp: RoboRioDaemonResponse;
+  simulator_req: SimulatorRequest;
+  simulator_rsp: SimulatorResponse;
+  unknown_req: UnknownGrappleDeviceRequest;
+  unknown_rsp: UnknownGrappleDeviceResponse;
 }
-export interface FlexiCanStatus {}
-export interface LaserCanRoi {
-  h: number;
-  w: number;
-  x: number;
-  y: number;
+/**
+ * A device event, along with the address of the provider the device is on
+ */
+export interface ProviderDeviceEvent {
+  event: DeviceEvent;
+  provider: string;
 }
-export interface LaserCanStatus {
-  last_update?: LaserCanMeasurement | null;
+export interface DeviceInfo {
+  device_id?: number | null;
+  device_type: DeviceType;
+  firmware_version?: string | null;
+  is_dfu: boolean;
+  is_dfu_in_progress: boolean;
+  name?: string | null;
+  serial?: number | null;
+}
+export interface TaggedGrappleMessage {
+  device_id: number;
+  msg: GrappleDeviceMessage;
+}
+export interface UpdatePartV2Payload {
+  offset: number;
+  payload: number[];
+}
+export interface FlashParameters {
+  align: number;
+  flash_compat_version: number;
+  payload_len: number;
 }
 export interface LaserCanMeasurement {
   ambient: number;
@@ -956,22 +1605,11 @@ export interface LaserCanMeasurement {
   roi: LaserCanRoi;
   status: number;
 }
-export interface LightReleaseResponse {
-  html_url: string;
-  name: string;
-  published_at: string;
-  tag_name: string;
-}
-export interface MitocandriaSwitchableChannelRequest {
-  channel: number;
-  enabled: boolean;
-}
-export interface MitocandriaAdjustableChannelRequest {
-  channel: number;
-  voltage: number;
-}
-export interface MitocandriaStatus {
-  last_update?: MitocandriaStatusFrame | null;
+export interface LaserCanRoi {
+  h: number;
+  w: number;
+  x: number;
+  y: number;
 }
 export interface MitocandriaStatusFrame {
   /**
@@ -986,44 +1624,13 @@ export interface MitocandriaStatusFrame {
     MitocandriaChannelStatus
   ];
 }
-export interface ProviderInfo {
-  address: string;
-  connected: boolean;
-  description: string;
-  ty: string;
-}
-export interface DeviceInfo {
-  device_id?: number | null;
-  device_type: DeviceType;
-  firmware_version?: string | null;
-  is_dfu: boolean;
-  is_dfu_in_progress: boolean;
-  name?: string | null;
-  serial?: number | null;
-}
-export interface MessageId {
-  api_class: number;
-  api_index: number;
-  device_id: number;
-  device_type: number;
-  manufacturer: number;
-}
-export interface RoboRIOStatus {
-  using_daemon: boolean;
-}
-export interface MailboxItem {
-  grpl_defrag?: GrappleDeviceMessage | null;
-  raw: BridgedCANMessage;
-  seq: number;
-}
-export interface UpdatePartV2Payload {
-  offset: number;
-  payload: number[];
+export interface MitocandriaSwitchableChannelRequest {
+  channel: number;
+  enabled: boolean;
 }
-export interface FlashParameters {
-  align: number;
-  flash_compat_version: number;
-  payload_len: number;
+export interface MitocandriaAdjustableChannelRequest {
+  channel: number;
+  voltage: number;
 }
 export interface MitocandriaAdjustableChannelCalibrationRequest {
   offset_mv: number;
@@ -1034,6 +1641,13 @@ export interface EncapsulatedMesssage {
   id: MessageId;
   timestamp: number;
 }
+export interface MessageId {
+  api_class: number;
+  api_index: number;
+  device_id: number;
+  device_type: number;
+  manufacturer: number;
+}
 export interface JMSElectronicsStatus {
   /**
    * @minItems 2
@@ -1051,8 +1665,272 @@ export interface Colour {
   green: number;
   red: number;
 }
+export interface LightReleaseResponse {
+  assets?: ReleaseAsset[];
+  html_url: string;
+  name: string;
+  prerelease?: boolean;
+  published_at: string;
+  tag_name: string;
+}
+export interface ReleaseAsset {
+  browser_download_url: string;
+  name: string;
+  size?: number;
+}
+export interface FlexiCanStatus {
+  channels: FlexiCanChannelStatus[];
+}
+export interface FlexiCanChannelStatus {
+  bridging: boolean;
+  channel: number;
+  /**
+   * Number of frames bridged from this channel since the device was found
+   */
+  frames_bridged: number;
+  last_frame?: EncapsulatedMesssage | null;
+  name?: string | null;
+}
+export interface LaserCanStatus {
+  config?: LaserCanConfig | null;
+  last_update?: LaserCanMeasurement | null;
+}
+/**
+ * The configuration currently stored on the LaserCAN, as reported in its measurement frames.
+ */
+export interface LaserCanConfig {
+  budget: LaserCanTimingBudget;
+  mode: LaserCanRangingMode;
+  roi: LaserCanRoi;
+}
+export interface LaserCanHistory {
+  /**
+   * Distance statistics over valid (status 0) measurements only
+   */
+  distance_mm?: SeriesStats | null;
+  series: HistoryItemFor_LaserCanMeasurement[];
+  /**
+   * Number of measurements with each status code
+   */
+  status_counts: {
+    [k: string]: number;
+  };
+}
+export interface SeriesStats {
+  count: number;
+  max: number;
+  mean: number;
+  min: number;
+  /**
+   * Population standard deviation
+   */
+  stddev: number;
+}
+export interface HistoryItemFor_LaserCanMeasurement {
+  /**
+   * Milliseconds since the history was created
+   */
+  timestamp: number;
+  value: LaserCanMeasurement;
+}
+export interface MitocandriaStatus {
+  last_update?: MitocandriaStatusFrame | null;
+}
+export interface MitocandriaChannelHistory {
+  channel: number;
+  current?: SeriesStats | null;
+  series: MitocandriaChannelSample[];
+  voltage?: SeriesStats | null;
+}
+export interface MitocandriaChannelSample {
+  /**
+   * Current in mA
+   */
+  current: number;
+  enabled?: boolean | null;
+  /**
+   * Milliseconds since the history was created
+   */
+  timestamp: number;
+  /**
+   * Voltage in mV, for adjustable channels
+   */
+  voltage?: number | null;
+}
+/**
+ * Where update checks and firmware downloads are fetched from, shared by everything that looks for releases.
+ */
+export interface UpdateSourceConfig {
+  channel: UpdateChannel;
+  /**
+   * The base of the GitHub-style release API. Can be pointed at a local mirror for events without internet access.
+   */
+  endpoint: string;
+  /**
+   * Proxy for all update requests, e.g. `http://proxy.local:3128`
+   */
+  proxy?: string | null;
+}
+/**
+ * How requests to devices are retried when they go unanswered.
+ */
+export interface RetryPolicy {
+  /**
+   * How many times to send the request, including the first
+   */
+  attempts: number;
+  /**
+   * Delay before the first retry. Doubles for each retry after that, up to `max_backoff_ms`.
+   */
+  backoff_ms: number;
+  /**
+   * Fraction (0 to 1) of each delay that's random, so requests that failed together don't all retry together
+   */
+  jitter: number;
+  max_backoff_ms: number;
+  /**
+   * How long to wait for each reply
+   */
+  timeout_ms: number;
+}
+/**
+ * How long a device can go without answering enumeration requests before it's considered offline, and then forgotten altogether.
+ */
+export interface AgeOffConfig {
+  /**
+   * Offline devices are kept until the provider is reset if this isn't set
+   */
+  forget_after_ms?: number | null;
+  offline_after_ms: number;
+}
+export interface ConfigSnapshot {
+  devices: DeviceSnapshot[];
+}
+export interface DeviceSnapshot {
+  config?: DeviceConfig | null;
+  device_id?: number | null;
+  device_type: DeviceType;
+  name?: string | null;
+  serial: number;
+}
+export interface MitocandriaChannelConfig {
+  channel: number;
+  enabled?: boolean | null;
+  /**
+   * Voltage setpoint in mV, for adjustable channels
+   */
+  voltage?: number | null;
+}
+export interface ImportedFirmwareBundle {
+  error?: string | null;
+  file: string;
+  targets: FirmwareBundleTargetInfo[];
+}
+export interface FirmwareBundleTargetInfo {
+  model?: GrappleModelId | null;
+  version?: string | null;
+}
+export interface ProviderInfo {
+  address: string;
+  connected: boolean;
+  description: string;
+  ty: string;
+}
+/**
+ * The result of an RPC call to a device, along with how hard it was to get
+ */
+export interface CallReport {
+  result: unknown;
+  stats: RequestStats;
+}
+/**
+ * How many requests an RPC call made to the device, and how many attempts they took between them. More attempts than requests points to a flaky bus rather than a dead device.
+ */
+export interface RequestStats {
+  attempts: number;
+  requests: number;
+}
+export interface DevicePresence {
+  /**
+   * RFC3339 timestamp of the last time the device answered enumeration
+   */
+  last_seen: string;
+  /**
+   * False once the device has stopped answering enumeration requests
+   */
+  online: boolean;
+}
+export interface IdReassignment {
+  domain: string;
+  new_id: number;
+  old_id: number;
+  serial: number;
+}
+export interface ConfigApplyResult {
+  error?: string | null;
+  serial: number;
+}
+export interface BulkUpgradeStatus {
+  devices: BulkUpgradeDeviceStatus[];
+  model: GrappleModelId;
+  running: boolean;
+}
+export interface BulkUpgradeDeviceStatus {
+  domain: string;
+  serial: number;
+  state: BulkUpgradeState;
+}
+export interface FirmwareBundleInfo {
+  release_notes?: string | null;
+  targets: FirmwareBundleTargetInfo[];
+  version?: string | null;
+}
+export interface ReplayStatus {
+  mode: ReplayMode;
+  position: number;
+  total: number;
+}
+export interface RoboRIOStatus {
+  using_daemon: boolean;
+}
+export interface MailboxItem {
+  grpl_defrag?: GrappleDeviceMessage | null;
+  raw: BridgedCANMessage;
+  seq: number;
+}
 export interface BridgedCANMessage {
   data: number[];
   id: MessageId;
   timestamp: number;
 }
+export interface RecordingStatus {
+  format: RecordingFormat;
+  messages: number;
+  path: string;
+}
+export interface SimulatedDevice {
+  /**
+   * The ID and name last committed to EEPROM, which the device comes back with after a power cycle
+   */
+  committed_id: number;
+  committed_name: string;
+  device_id: number;
+  /**
+   * Number of firmware bytes received during the current field upgrade
+   */
+  firmware_received: number;
+  is_dfu: boolean;
+  is_dfu_in_progress: boolean;
+  name: string;
+  serial: number;
+  state: SimulatedDeviceState;
+  version: string;
+}
+export interface SimulatedFlexiCanChannel {
+  bridging: boolean;
+  /**
+   * Number of frames bridged so far, also used as the frame payload
+   */
+  frames_sent: number;
+  name: string;
+}